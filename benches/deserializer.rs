@@ -0,0 +1,136 @@
+// 反序列化器扫描字符串的基准测试，运行 `cargo bench --bench deserializer`。
+//
+// 输入是几十 MB 的数组，分别测量 `ch05_02_deserializer::from_slice` 和
+// 作为参照的 `serde_json::from_slice`。`wide` 一组测量跳过未知字段的速度，
+// `numbers` 和 `pretty` 两组是由数字、逗号、括号和空白组成的大数组
+use std::collections::BTreeMap;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use serde_learn::{ch05_01_serializer, ch05_02_deserializer};
+
+const ITERATIONS: u32 = 10;
+
+#[derive(Serialize, Deserialize)]
+struct Record {
+    id: u64,
+    name: String,
+    email: String,
+    bio: String,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Picked<'a> {
+    id: u64,
+    name: &'a str,
+    email: &'a str,
+}
+
+// 报告最快的一次而不是平均值，减少机器上其他负载的干扰
+fn bench(name: &str, input: &[u8], mut f: impl FnMut(&[u8]) -> usize) {
+    black_box(f(input));
+    let mut per_iter = Duration::MAX;
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        black_box(f(black_box(input)));
+        per_iter = per_iter.min(start.elapsed());
+    }
+    let throughput = input.len() as f64 / per_iter.as_secs_f64() / (1 << 20) as f64;
+    println!("{:<28} {:>12?} {:>10.1} MiB/s", name, per_iter, throughput);
+}
+
+fn main() {
+    // 长短不一、不含转义的字符串
+    let strings: Vec<String> = (0..400_000)
+        .map(|i| "lorem ipsum dolor sit amet ".repeat(i % 7 + 1))
+        .collect();
+    let strings = ch05_01_serializer::to_vec(&strings).unwrap();
+
+    // 字符串中偶尔有转义
+    let records: Vec<Record> = (0..200_000u64)
+        .map(|i| Record {
+            id: i,
+            name: format!("user {}", i),
+            email: format!("user{}@example.com", i),
+            bio: format!("line one\nline \"two\" of record {}, {}", i, "x".repeat(64)),
+        })
+        .collect();
+    let records = ch05_01_serializer::to_vec(&records).unwrap();
+
+    // 200 个字段的对象，只读取其中 3 个，其余字段由 `deserialize_ignored_any`
+    // 跳过
+    let wide: Vec<BTreeMap<String, serde_json::Value>> = (0..5_000u64)
+        .map(|i| {
+            let mut map = BTreeMap::new();
+            for field in 0..197 {
+                let value = match field % 4 {
+                    0 => json!(i * field),
+                    1 => json!(field as f64 / 3.0),
+                    2 => json!(format!("field {} of \"{}\"", field, i)),
+                    _ => json!({ "nested": [1, 2, { "deep": null }] }),
+                };
+                map.insert(format!("f{:03}", field), value);
+            }
+            map.insert("id".to_owned(), json!(i));
+            map.insert("name".to_owned(), json!(format!("user {}", i)));
+            map.insert("email".to_owned(), json!(format!("user{}@example.com", i)));
+            map
+        })
+        .collect();
+    let wide = ch05_01_serializer::to_vec(&wide).unwrap();
+
+    // 紧凑格式的大数组，几乎全是数字和逗号
+    let numbers: Vec<u64> = (0..4_000_000u64)
+        .map(|i| i.wrapping_mul(2_654_435_761) % 1_000_000)
+        .collect();
+    let numbers = ch05_01_serializer::to_vec(&numbers).unwrap();
+
+    // 四个空格缩进、嵌套较深的大数组，空白占了大半，每段空白有十几个字节
+    let pretty: Vec<Vec<Vec<[u32; 3]>>> = (0..2_000u32)
+        .map(|i| (0..10).map(|j| (0..10).map(|k| [i, j, k]).collect()).collect())
+        .collect();
+    let mut ser = ch05_01_serializer::Serializer::with_formatter(
+        Vec::new(),
+        ch05_01_serializer::PrettyFormatter::with_indent(b"    "),
+    );
+    pretty.serialize(&mut ser).unwrap();
+    let pretty = ser.into_inner().unwrap();
+
+    println!("{:<28} {:>12} {:>16}", "benchmark", "time/iter", "throughput");
+    bench("strings/serde_learn", &strings, |input| {
+        ch05_02_deserializer::from_slice::<Vec<&str>>(input).unwrap().len()
+    });
+    bench("strings/serde_json", &strings, |input| {
+        serde_json::from_slice::<Vec<&str>>(input).unwrap().len()
+    });
+    bench("strings/serde_learn/reader", &strings, |input| {
+        ch05_02_deserializer::from_reader::<_, Vec<String>>(input).unwrap().len()
+    });
+    bench("records/serde_learn", &records, |input| {
+        ch05_02_deserializer::from_slice::<Vec<Record>>(input).unwrap().len()
+    });
+    bench("records/serde_json", &records, |input| {
+        serde_json::from_slice::<Vec<Record>>(input).unwrap().len()
+    });
+    bench("wide/serde_learn", &wide, |input| {
+        ch05_02_deserializer::from_slice::<Vec<Picked>>(input).unwrap().len()
+    });
+    bench("wide/serde_json", &wide, |input| {
+        serde_json::from_slice::<Vec<Picked>>(input).unwrap().len()
+    });
+    bench("numbers/serde_learn", &numbers, |input| {
+        ch05_02_deserializer::from_slice::<Vec<u64>>(input).unwrap().len()
+    });
+    bench("numbers/serde_json", &numbers, |input| {
+        serde_json::from_slice::<Vec<u64>>(input).unwrap().len()
+    });
+    bench("pretty/serde_learn", &pretty, |input| {
+        ch05_02_deserializer::from_slice::<Vec<Vec<Vec<[u32; 3]>>>>(input).unwrap().len()
+    });
+    bench("pretty/serde_json", &pretty, |input| {
+        serde_json::from_slice::<Vec<Vec<Vec<[u32; 3]>>>>(input).unwrap().len()
+    });
+}
@@ -0,0 +1,70 @@
+// 序列化器数字输出的基准测试，运行 `cargo bench --bench serializer`。
+//
+// 每组数据分别测量：
+// - `to_string`：修改前的 `ch05_01_serializer::to_string`，代码见
+//   `baseline/mod.rs`
+// - `serde_learn`：`ch05_01_serializer::to_vec`
+// - `serde_json`：作为参照
+use std::hint::black_box;
+use std::time::Instant;
+
+use serde::Serialize;
+use serde_learn::ch05_01_serializer;
+
+mod baseline;
+
+const ITERATIONS: u32 = 200;
+
+#[derive(Serialize)]
+struct Record {
+    id: u64,
+    offset: i64,
+    score: f64,
+    ratio: f32,
+    tags: Vec<u32>,
+}
+
+fn records() -> Vec<Record> {
+    (0..10_000u64)
+        .map(|i| Record {
+            id: i * 7919,
+            offset: -(i as i64) * 104_729,
+            score: i as f64 / 7.0,
+            ratio: 1.0 / (i + 1) as f32,
+            tags: (0..4).map(|t| (i * 31 + t) as u32).collect(),
+        })
+        .collect()
+}
+
+fn bench(name: &str, mut f: impl FnMut() -> usize) {
+    // 预热，同时检查输出长度
+    let len = f();
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(f());
+    }
+    let per_iter = start.elapsed() / ITERATIONS;
+    let throughput = len as f64 / per_iter.as_secs_f64() / (1 << 20) as f64;
+    println!("{:<24} {:>12?} {:>10.1} MiB/s", name, per_iter, throughput);
+}
+
+fn main() {
+    let ints: Vec<i64> = (0..100_000i64)
+        .map(|i| i.wrapping_mul(0x9E37_79B9_7F4A_7C15u64 as i64) >> (i % 64))
+        .collect();
+    let floats: Vec<f64> = (1..100_001).map(|i| 1.0 / f64::from(i) * 1e3).collect();
+    let records = records();
+
+    println!("{:<24} {:>12} {:>16}", "benchmark", "time/iter", "throughput");
+    bench("ints/to_string", || baseline::to_string(black_box(&ints)).unwrap().len());
+    bench("ints/serde_learn", || ch05_01_serializer::to_vec(black_box(&ints)).unwrap().len());
+    bench("ints/serde_json", || serde_json::to_vec(black_box(&ints)).unwrap().len());
+
+    bench("floats/to_string", || baseline::to_string(black_box(&floats)).unwrap().len());
+    bench("floats/serde_learn", || ch05_01_serializer::to_vec(black_box(&floats)).unwrap().len());
+    bench("floats/serde_json", || serde_json::to_vec(black_box(&floats)).unwrap().len());
+
+    bench("records/to_string", || baseline::to_string(black_box(&records)).unwrap().len());
+    bench("records/serde_learn", || ch05_01_serializer::to_vec(black_box(&records)).unwrap().len());
+    bench("records/serde_json", || serde_json::to_vec(black_box(&records)).unwrap().len());
+}
@@ -0,0 +1,70 @@
+// 检查任意输入都不会让反序列化器 panic：
+//
+//     cargo +nightly fuzz run from_slice fuzz/corpus/from_slice
+//
+// 不需要 nightly 和 libFuzzer 的离线回放见
+// `ch05_02_deserializer::test_fuzz_corpus`，它使用同一个种子语料库
+#![no_main]
+
+use std::collections::BTreeMap;
+
+use libfuzzer_sys::fuzz_target;
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+use serde_learn::ch05_01_serializer::{self as ser, BytesMode};
+use serde_learn::ch05_02_deserializer::{self as de, Deserializer, Limits, StreamDeserializer};
+use serde_learn::ch05_03_value::Value;
+
+// 覆盖各种 `deserialize_*` 方法的类型
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Typed<'a> {
+    b: bool,
+    i: i8,
+    u: u16,
+    f: f32,
+    c: char,
+    s: &'a str,
+    o: Option<String>,
+    #[serde(with = "serde_bytes")]
+    bytes: Vec<u8>,
+    e: Vec<E>,
+    keys: BTreeMap<i32, ()>,
+    #[serde(flatten)]
+    rest: BTreeMap<String, IgnoredAny>,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+enum E {
+    Unit,
+    Newtype(u64),
+    Tuple(i64, f64),
+    Struct { a: Value },
+}
+
+fuzz_target!(|data: &[u8]| {
+    // 结果无关紧要，只要不 panic
+    if let Ok(value) = de::from_slice::<Value>(data) {
+        let _ = ser::to_vec(&value);
+    }
+    let _ = de::from_slice::<Typed>(data);
+    let _ = de::from_slice::<IgnoredAny>(data);
+    let _ = de::from_reader::<_, Value>(data);
+    if let Ok(s) = std::str::from_utf8(data) {
+        let _ = de::from_str::<Value>(s);
+    }
+    for value in StreamDeserializer::<_, Value>::new(de::SliceRead::new(data)) {
+        let _ = value;
+    }
+
+    let mut de = Deserializer::from_slice(data);
+    de.set_bytes_mode(BytesMode::Base64);
+    de.set_limits(Limits {
+        max_string_len: Some(16),
+        max_number_len: Some(16),
+        max_elements: Some(4),
+        max_input_len: Some(64),
+    });
+    let _ = Typed::deserialize(&mut de);
+});
@@ -15,7 +15,7 @@ struct Point {
 #[allow(unused, dead_code)]
 
 #[derive(Serialize, Deserialize, Debug)]
-enum State {
+pub(crate) enum State {
     Running(i32),
     Stop,
     #[serde(skip)]
@@ -1,4 +1,6 @@
-use serde::{Serialize, Serializer};
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize, Serializer};
 use serde::ser::{SerializeStruct, SerializeTupleStruct, SerializeStructVariant, SerializeTupleVariant};
 
 // impl Serialize for i32 {
@@ -139,10 +141,12 @@ impl Serialize for E {
     }
 }
 
-#[derive(Serialize)]
+// JSON 中的字节数组（数字数组或 base64 字符串）都要解码，无法借用输入，
+// 所以反序列化时用 Cow 持有解码后的数据
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
 struct Efficient<'a> {
-    #[serde(with = "serde_bytes")]
-    bytes: &'a [u8],
+    #[serde(with = "serde_bytes", borrow)]
+    bytes: Cow<'a, [u8]>,
     byte_buf: Vec<u8>,
 }
 
@@ -187,7 +191,7 @@ impl <'a> Serialize for Efficient2<'a> {
 
 use std::fmt;
 use std::marker::PhantomData;
-use serde::de::{self, Deserialize, Deserializer, Visitor, SeqAccess, MapAccess};
+use serde::de::{self, Deserializer, Visitor, SeqAccess, MapAccess};
 
 struct MyMap<K, V>(PhantomData<K>, PhantomData<V>);
 
@@ -410,4 +414,32 @@ mod tests {
         let r2: Duration = serde_json::from_str(json2).unwrap();
         println!("Duration = {:?}", r2);
     }
+
+    #[test]
+    fn efficient_round_trip() {
+        use crate::ch05_01_serializer::{self as ser, BytesMode};
+        use crate::ch05_02_deserializer as de;
+
+        let efficient = Efficient {
+            bytes: Cow::Borrowed(b"\x00\xffhi"),
+            byte_buf: vec![1, 2],
+        };
+        let expected = [
+            (BytesMode::Array, r#"{"bytes":[0,255,104,105],"byte_buf":[1,2]}"#),
+            (BytesMode::Base64, r#"{"bytes":"AP9oaQ==","byte_buf":[1,2]}"#),
+            (BytesMode::Hex, r#"{"bytes":"00ff6869","byte_buf":[1,2]}"#),
+        ];
+        for &(mode, json) in &expected {
+            let mut output = Vec::new();
+            let mut serializer = ser::Serializer::new(&mut output);
+            serializer.set_bytes_mode(mode);
+            efficient.serialize(&mut serializer).unwrap();
+            serializer.flush().unwrap();
+            assert_eq!(json.as_bytes(), &output[..]);
+
+            let mut deserializer = de::Deserializer::from_str(json);
+            deserializer.set_bytes_mode(mode);
+            assert_eq!(efficient, Efficient::deserialize(&mut deserializer).unwrap());
+        }
+    }
 }
@@ -1,13 +1,66 @@
 use std;
 use std::fmt::{self, Display};
+use std::io;
 
 use serde::{de, ser};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
-// 一些额外的错误
-#[derive(Clone, Debug, PartialEq)]
-pub enum Error {
+// 序列化、反序列化过程中的错误：错误的种类以及错误发生在输入中的位置。
+//
+// 反序列化产生的错误都带有位置，序列化产生的错误没有位置
+#[derive(Debug)]
+pub struct Error {
+    code: ErrorCode,
+    position: Option<Position>,
+    // 出错位置上实际遇到的字符，只有语法检查失败时才记录
+    found: Option<char>,
+}
+
+// 错误的分类，调用方可以据此决定重试还是拒绝输入
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Category {
+    // 读写底层 `io::Read` / `io::Write` 失败
+    Io,
+    // 输入不是合法的 JSON
+    Syntax,
+    // 输入是合法的 JSON，但与目标类型不匹配，例如类型错误、缺少字段、
+    // 数字超出范围；或者超出了反序列化器的资源限制
+    Data,
+    // 输入在一个值结束之前就结束了，通常意味着输入被截断
+    Eof,
+}
+
+// 错误在输入中的位置
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Position {
+    // 从 1 开始的行号
+    pub line: usize,
+    // 从 1 开始的列号，按字符计数
+    pub column: usize,
+    // 从 0 开始的字节偏移
+    pub offset: usize,
+}
+
+impl Position {
+    // 根据字节偏移计算 `input` 中的行号和列号。列号按字符计数，UTF-8 的
+    // 后续字节（0b10xx_xxxx）不计入
+    pub(crate) fn from_offset(input: &[u8], offset: usize) -> Self {
+        let consumed = input.get(..offset).unwrap_or(input);
+        let line_start = consumed.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+        Position {
+            line: 1 + consumed.iter().filter(|&&b| b == b'\n').count(),
+            column: 1 + consumed[line_start..].iter().filter(|&&b| b & 0xC0 != 0x80).count(),
+            offset,
+        }
+    }
+}
+
+// 错误的种类
+//
+// `io::Error` 既不能 Clone 也不能比较，因此 ErrorCode 只实现了 Debug
+#[derive(Debug)]
+pub enum ErrorCode {
     // One or more variants that can be created by data structures through the
     // `ser::Error` and `de::Error` traits. For example the Serialize impl for
     // Mutex<T> might return an error because the mutex is poisoned, or the
@@ -15,6 +68,9 @@ pub enum Error {
     // field is missing.
     Message(String),
 
+    // 读写底层 `io::Read` / `io::Write` 时发生的错误
+    Io(io::Error),
+
     // Zero or more variants that can be created directly by the Serializer and
     // Deserializer without going through `ser::Error` and `de::Error`. These
     // are specific to the format, in this case JSON.
@@ -22,7 +78,44 @@ pub enum Error {
     Syntax,
     ExpectedBoolean,
     ExpectedInteger,
+    ExpectedFloat,
+    // 不符合数字语法，例如 `1e`、`-`，严格模式下还有 `007`、`+1`、`.5`、`1.`、
+    // `0x1F`。见 `ch05_02_deserializer::NumberMode`
+    InvalidNumber,
+    // 数字超出目标类型的范围（浮点数为溢出到无穷大），附带目标类型的名称
+    NumberOutOfRange(&'static str),
     ExpectedString,
+    // 字符串中出现了未转义的控制字符（U+0000 到 U+001F）
+    ControlCharacterInString,
+    // `\` 之后不是合法的转义字符
+    InvalidEscape,
+    // 字节切片或读取器中的字符串不是合法的 UTF-8
+    InvalidUtf8,
+    // `\u` 之后不是 4 位十六进制数
+    InvalidUnicodeEscape,
+    // `\uXXXX` 转义出的 UTF-16 代理没有成对出现
+    LoneSurrogate,
+    // 按 `BytesMode::Base64` 读取字节数组时，字符串不是带填充的标准 base64
+    InvalidBase64,
+    // 按 `BytesMode::Hex` 读取字节数组时，字符串不是偶数位的十六进制数
+    InvalidHex,
+    // 对象的键只能是字符串，或者能无歧义地写成字符串的整数、布尔值、字符
+    // 和 unit variant
+    KeyMustBeAString,
+    // `NonFinite::Error` 下序列化了 NaN 或无穷大
+    FloatMustBeFinite,
+    // 整数或布尔值类型的键，引号内不是合法的整数或布尔值，例如 `{"1x": 0}`
+    InvalidMapKey,
+    // 数组、对象和枚举的嵌套超过了反序列化器的深度限制
+    RecursionLimitExceeded,
+    // 字符串解码后的长度超过了 `Limits::max_string_len`
+    StringTooLong,
+    // 数字的文本长度超过了 `Limits::max_number_len`
+    NumberTooLong,
+    // 数组或对象的元素个数超过了 `Limits::max_elements`
+    TooManyElements,
+    // 输入的长度超过了 `Limits::max_input_len`
+    InputTooLong,
     ExpectedNull,
     ExpectedArray,
     ExpectedArrayComma,
@@ -35,27 +128,204 @@ pub enum Error {
     TrailingCharacters,
 }
 
+impl Error {
+    pub fn new(code: ErrorCode, position: Option<Position>) -> Self {
+        Error {
+            code,
+            position,
+            found: None,
+        }
+    }
+
+    // 记录出错位置上实际遇到的字符
+    pub(crate) fn with_found(self, found: char) -> Self {
+        Error {
+            found: Some(found),
+            ..self
+        }
+    }
+
+    // 错误的种类
+    pub fn code(&self) -> &ErrorCode {
+        &self.code
+    }
+
+    // 错误的分类。
+    //
+    // 期望某种类型却遇到了另一个 JSON 值时属于 Data，遇到的根本不是 JSON 值
+    // 的开头时属于 Syntax
+    pub fn classify(&self) -> Category {
+        match self.code {
+            ErrorCode::Io(_) => Category::Io,
+            ErrorCode::Eof => Category::Eof,
+            // 输入本身是合法的 JSON，只是内容无法转换为目标类型
+            ErrorCode::Message(_)
+            | ErrorCode::NumberOutOfRange(_)
+            | ErrorCode::InvalidBase64
+            | ErrorCode::InvalidHex
+            | ErrorCode::KeyMustBeAString
+            | ErrorCode::FloatMustBeFinite
+            | ErrorCode::InvalidMapKey
+            // 超出资源限制的输入可能是合法的 JSON，调用方应当按“输入过大”拒绝，
+            // 而不是当作格式错误
+            | ErrorCode::RecursionLimitExceeded
+            | ErrorCode::StringTooLong
+            | ErrorCode::NumberTooLong
+            | ErrorCode::TooManyElements
+            | ErrorCode::InputTooLong => Category::Data,
+            ErrorCode::ExpectedBoolean
+            | ErrorCode::ExpectedInteger
+            | ErrorCode::ExpectedFloat
+            | ErrorCode::ExpectedString
+            | ErrorCode::ExpectedNull
+            | ErrorCode::ExpectedArray
+            | ErrorCode::ExpectedMap
+            | ErrorCode::ExpectedEnum => match self.found {
+                Some(ch) if !starts_value(ch) => Category::Syntax,
+                _ => Category::Data,
+            },
+            _ => Category::Syntax,
+        }
+    }
+
+    // 错误发生的位置，序列化产生的错误没有位置
+    pub fn position(&self) -> Option<Position> {
+        self.position
+    }
+
+    // 错误所在的行号，从 1 开始；没有位置时为 0
+    pub fn line(&self) -> usize {
+        self.position.map_or(0, |position| position.line)
+    }
+
+    // 错误所在的列号，从 1 开始；没有位置时为 0
+    pub fn column(&self) -> usize {
+        self.position.map_or(0, |position| position.column)
+    }
+
+    // 错误所在的字节偏移；没有位置时为 0
+    pub fn offset(&self) -> usize {
+        self.position.map_or(0, |position| position.offset)
+    }
+
+    // `de::Error::custom` 等途径创建的错误不知道自己的位置，由反序列化器在
+    // 错误传出时补上
+    pub(crate) fn fix_position<F>(self, f: F) -> Self
+    where
+        F: FnOnce() -> Position,
+    {
+        if self.position.is_some() {
+            self
+        } else {
+            Error {
+                position: Some(f()),
+                ..self
+            }
+        }
+    }
+}
+
+// 能够作为一个 JSON 值开头的字符
+fn starts_value(ch: char) -> bool {
+    matches!(ch, 'n' | 't' | 'f' | '"' | '[' | '{' | '-' | '0'..='9')
+}
+
+impl From<ErrorCode> for Error {
+    fn from(code: ErrorCode) -> Self {
+        Error::new(code, None)
+    }
+}
+
 impl ser::Error for Error {
     fn custom<T: Display>(msg: T) -> Self {
-        Error::Message(msg.to_string())
+        ErrorCode::Message(msg.to_string()).into()
     }
 }
 
 impl de::Error for Error {
     fn custom<T: Display>(msg: T) -> Self {
-        Error::Message(msg.to_string())
+        ErrorCode::Message(msg.to_string()).into()
     }
 }
 
 impl Display for Error {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(&self.code, formatter)?;
+        if let Some(found) = self.found {
+            write!(formatter, ", found {:?}", found)?;
+        }
+        if let Some(position) = self.position {
+            write!(
+                formatter,
+                " at line {} column {}",
+                position.line, position.column
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for ErrorCode {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Error::Message(msg) => formatter.write_str(msg),
-            Error::Eof => formatter.write_str("unexpected end of input"),
-            /* and so forth */
-            _ => formatter.write_str("unimplemented!()"),
+            ErrorCode::Message(msg) => formatter.write_str(msg),
+            ErrorCode::Io(err) => Display::fmt(err, formatter),
+            ErrorCode::Eof => formatter.write_str("unexpected end of input"),
+            ErrorCode::Syntax => formatter.write_str("expected a JSON value"),
+            ErrorCode::ExpectedBoolean => formatter.write_str("expected `true` or `false`"),
+            ErrorCode::ExpectedInteger => formatter.write_str("expected an integer"),
+            ErrorCode::ExpectedFloat => formatter.write_str("expected a number"),
+            ErrorCode::InvalidNumber => formatter.write_str("invalid number"),
+            ErrorCode::NumberOutOfRange(ty) => write!(formatter, "number out of range for {}", ty),
+            ErrorCode::ExpectedString => formatter.write_str("expected a string"),
+            ErrorCode::ControlCharacterInString => {
+                formatter.write_str("control character (U+0000 to U+001F) must be escaped in string")
+            }
+            ErrorCode::InvalidEscape => formatter.write_str("invalid escape sequence in string"),
+            ErrorCode::InvalidUtf8 => formatter.write_str("invalid UTF-8 in string"),
+            ErrorCode::InvalidUnicodeEscape => {
+                formatter.write_str("invalid unicode escape, expected 4 hex digits after `\\u`")
+            }
+            ErrorCode::LoneSurrogate => formatter.write_str("lone UTF-16 surrogate in unicode escape"),
+            ErrorCode::InvalidBase64 => formatter.write_str("invalid base64 in byte string"),
+            ErrorCode::InvalidHex => formatter.write_str("invalid hex in byte string"),
+            ErrorCode::KeyMustBeAString => formatter.write_str("key must be a string"),
+            ErrorCode::FloatMustBeFinite => formatter.write_str("float must be finite"),
+            ErrorCode::InvalidMapKey => {
+                formatter.write_str("invalid map key, expected a quoted integer or boolean")
+            }
+            ErrorCode::RecursionLimitExceeded => formatter.write_str("recursion limit exceeded"),
+            ErrorCode::StringTooLong => formatter.write_str("string exceeds the maximum length"),
+            ErrorCode::NumberTooLong => formatter.write_str("number exceeds the maximum length"),
+            ErrorCode::TooManyElements => {
+                formatter.write_str("array or object exceeds the maximum number of elements")
+            }
+            ErrorCode::InputTooLong => formatter.write_str("input exceeds the maximum length"),
+            ErrorCode::ExpectedNull => formatter.write_str("expected `null`"),
+            ErrorCode::ExpectedArray => formatter.write_str("expected an array"),
+            ErrorCode::ExpectedArrayComma => formatter.write_str("expected `,` or `]` after array element"),
+            ErrorCode::ExpectedArrayEnd => formatter.write_str("expected `]` at end of array"),
+            ErrorCode::ExpectedMap => formatter.write_str("expected an object"),
+            ErrorCode::ExpectedMapColon => formatter.write_str("expected `:` after object key"),
+            ErrorCode::ExpectedMapComma => formatter.write_str("expected `,` or `}` after object value"),
+            ErrorCode::ExpectedMapEnd => formatter.write_str("expected `}` at end of object"),
+            ErrorCode::ExpectedEnum => formatter.write_str("expected a string or an object for enum"),
+            ErrorCode::TrailingCharacters => formatter.write_str("trailing characters after JSON value"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.code {
+            ErrorCode::Io(err) => Some(err),
+            _ => None,
         }
     }
 }
 
-impl std::error::Error for Error {}
\ No newline at end of file
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        ErrorCode::Io(err).into()
+    }
+}
\ No newline at end of file
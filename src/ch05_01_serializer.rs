@@ -1,29 +1,358 @@
+use std::cmp;
+use std::fmt;
+use std::io;
+
+use serde::ser::Impossible;
 use serde::{ser, Serialize};
 
-use super::ch05_00_error::{Error, Result};
+use super::ch05_00_error::{Error, ErrorCode, Result};
+
+// 缓冲区积累到这个大小后写入底层的 writer
+const FLUSH_THRESHOLD: usize = 8 * 1024;
+
+pub struct Serializer<W, F = CompactFormatter> {
+    // 输出目标，可以是文件、socket、管道或者内存中的 `Vec<u8>`
+    writer: W,
+    // 序列化值时把 JSON 追加到这个缓冲区，积累到 `FLUSH_THRESHOLD` 后再一次性
+    // 写入 writer，避免每个逗号、引号都产生一次系统调用
+    buf: Vec<u8>,
+    // 决定数组、对象的标点和空白如何输出
+    formatter: F,
+    // 字符串的转义方式
+    escape: EscapeMode,
+    // 字节数组的输出形式
+    bytes: BytesMode,
+    // NaN 和无穷大的处理方式
+    non_finite: NonFinite,
+}
+
+// 字符串的转义方式。无论哪种方式，`"`、`\` 和控制字符（U+0000 到 U+001F）
+// 都会被转义，这是 JSON 的强制要求
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EscapeMode {
+    // 只转义强制要求的字符，其余字符原样输出
+    #[default]
+    Minimal,
+    // 另外把所有非 ASCII 字符转义为 `\uXXXX`（基本多文种平面以外的字符使用
+    // UTF-16 代理对），输出只包含 ASCII 字符
+    Ascii,
+    // 另外转义 `<`、`>`、`&` 以及 U+2028、U+2029，输出可以安全地嵌入
+    // HTML 的 `<script>` 标签
+    Html,
+}
+
+// `serialize_bytes` 的输出形式。`ch05_02_deserializer::Deserializer` 需要设置
+// 同样的形式才能读回
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BytesMode {
+    // 数字数组，例如 `[104,105]`
+    #[default]
+    Array,
+    // 带填充的标准 base64 字符串（RFC 4648），例如 `"aGk="`。体积约为原始
+    // 数据的 4/3，适合嵌入较大的二进制数据
+    Base64,
+    // 小写的十六进制字符串，例如 `"6869"`
+    Hex,
+}
+
+// JSON 的数字不能表示 NaN 和无穷大，序列化这些浮点数时的处理方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NonFinite {
+    // 返回 `ErrorCode::FloatMustBeFinite`
+    Error,
+    // 输出 `null`，与 `to_value` 的行为一致
+    #[default]
+    Null,
+    // 输出 JavaScript 的写法 `"NaN"`、`"Infinity"`、`"-Infinity"`
+    String,
+}
+
+impl<W> Serializer<W>
+where
+    W: io::Write,
+{
+    // 输出紧凑的 JSON。
+    //
+    // 输出先积累在内部的缓冲区中，序列化完成后必须调用 `flush` 或
+    // `into_inner` 才会写入 writer。drop 时不会写出缓冲区：序列化中途出错时，
+    // 写出的只是一段不完整的 JSON
+    pub fn new(writer: W) -> Self {
+        Serializer::with_formatter(writer, CompactFormatter)
+    }
+}
+
+impl<'a, W> Serializer<W, PrettyFormatter<'a>>
+where
+    W: io::Write,
+{
+    // 输出带两个空格缩进的 JSON
+    pub fn pretty(writer: W) -> Self {
+        Serializer::with_formatter(writer, PrettyFormatter::new())
+    }
+}
+
+impl<W, F> Serializer<W, F>
+where
+    W: io::Write,
+    F: Formatter,
+{
+    pub fn with_formatter(writer: W, formatter: F) -> Self {
+        Serializer {
+            writer,
+            buf: Vec::with_capacity(FLUSH_THRESHOLD),
+            formatter,
+            escape: EscapeMode::default(),
+            bytes: BytesMode::default(),
+            non_finite: NonFinite::default(),
+        }
+    }
+
+    // 设置字符串的转义方式，默认为 `EscapeMode::Minimal`
+    pub fn set_escape_mode(&mut self, escape: EscapeMode) {
+        self.escape = escape;
+    }
+
+    // 设置字节数组的输出形式，默认为 `BytesMode::Array`
+    pub fn set_bytes_mode(&mut self, bytes: BytesMode) {
+        self.bytes = bytes;
+    }
+
+    // 设置 NaN 和无穷大的处理方式，默认为 `NonFinite::Null`
+    pub fn set_non_finite(&mut self, non_finite: NonFinite) {
+        self.non_finite = non_finite;
+    }
+
+    // 把缓冲区中的内容全部写入 writer 并刷新 writer
+    pub fn flush(&mut self) -> Result<()> {
+        self.write_buf()?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    // 刷新缓冲区后取回 writer
+    pub fn into_inner(mut self) -> Result<W> {
+        self.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write_buf(&mut self) -> Result<()> {
+        self.writer.write_all(&self.buf)?;
+        self.buf.clear();
+        Ok(())
+    }
+
+    // 在复合类型的元素之间调用，缓冲区足够大时才真正写出
+    fn flush_if_full(&mut self) -> Result<()> {
+        if self.buf.len() >= FLUSH_THRESHOLD {
+            self.write_buf()?;
+        }
+        Ok(())
+    }
 
-pub struct Serializer {
-    // 此字符串初始化为空串，并在序列化值时附加JSON。 
-    output: String,
+    // 按照复合类型的长度提示预留缓冲区，避免输出过程中多次扩容。缓冲区积累到
+    // `FLUSH_THRESHOLD` 就会写出，预留的空间不超过这个大小，过大的提示也不会
+    // 导致大块的分配
+    fn reserve(&mut self, len: Option<usize>, min_element_len: usize) {
+        if let Some(len) = len {
+            let additional = len.saturating_mul(min_element_len);
+            self.buf.reserve(cmp::min(additional, FLUSH_THRESHOLD));
+        }
+    }
 }
 
 // 按照惯例，Serde序列化器的公共API是一个或多个`to_abc`
 // 函数，例如“ to_string”，“ to_bytes”或“ to_writer”，具体取决于
 // Rust类型的序列化程序能够产生输出。
 //
-// 这个基本的序列化程序仅支持`to_string`。
+// 所有的输出最终都经过 `to_writer`，其余函数只是选择不同的输出目标。
+//
+// 序列化不会 panic：写入失败、非字符串的键等错误都以 `Err` 返回。前提是
+// 被序列化类型的 `Serialize` 实现和 writer 本身不 panic
+pub fn to_writer<W, T>(writer: W, value: &T) -> Result<()>
+where
+    W: io::Write,
+    T: ?Sized + Serialize,
+{
+    let mut serializer = Serializer::new(writer);
+    value.serialize(&mut serializer)?;
+    serializer.flush()
+}
+
+pub fn to_vec<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: ?Sized + Serialize,
+{
+    let mut output = Vec::with_capacity(128);
+    to_writer(&mut output, value)?;
+    Ok(output)
+}
+
 pub fn to_string<T>(value: &T) -> Result<String>
 where
-    T: Serialize,
+    T: ?Sized + Serialize,
 {
-    let mut serializer = Serializer {
-        output: String::new(),
-    };
+    into_string(to_vec(value)?)
+}
+
+// 以下三个函数输出带缩进的 JSON，便于人工阅读
+pub fn to_writer_pretty<W, T>(writer: W, value: &T) -> Result<()>
+where
+    W: io::Write,
+    T: ?Sized + Serialize,
+{
+    let mut serializer = Serializer::pretty(writer);
     value.serialize(&mut serializer)?;
-    Ok(serializer.output)
+    serializer.flush()
+}
+
+pub fn to_vec_pretty<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: ?Sized + Serialize,
+{
+    let mut output = Vec::with_capacity(128);
+    to_writer_pretty(&mut output, value)?;
+    Ok(output)
+}
+
+pub fn to_string_pretty<T>(value: &T) -> Result<String>
+where
+    T: ?Sized + Serialize,
+{
+    into_string(to_vec_pretty(value)?)
+}
+
+// 输出以换行分隔的 JSON（NDJSON）：每个值以紧凑格式单独占一行。紧凑格式
+// 中字符串里的换行总是被转义，因此每一行恰好是一个完整的值
+pub fn to_writer_lines<W, I>(writer: W, values: I) -> Result<()>
+where
+    W: io::Write,
+    I: IntoIterator,
+    I::Item: Serialize,
+{
+    let mut serializer = Serializer::new(writer);
+    for value in values {
+        value.serialize(&mut serializer)?;
+        serializer.buf.push(b'\n');
+        serializer.flush_if_full()?;
+    }
+    serializer.flush()
+}
+
+fn into_string(output: Vec<u8>) -> Result<String> {
+    // 序列化器只会写出完整的 UTF-8 字符串片段，这里不会失败
+    String::from_utf8(output).map_err(|err| ErrorCode::Message(err.to_string()).into())
+}
+
+// 写入 `fmt::Write`，例如 `String` 或者 `fmt::Formatter`，便于在 `Display`
+// 实现中直接输出 JSON
+pub fn to_fmt<F, T>(output: &mut F, value: &T) -> Result<()>
+where
+    F: ?Sized + fmt::Write,
+    T: ?Sized + Serialize,
+{
+    to_writer(FmtWriter { output }, value)
+}
+
+// 把 `io::Write` 的写入转发给 `fmt::Write`。序列化器每次交给 writer 的都是
+// 若干个完整的 UTF-8 片段，因此收到的字节总是合法的 UTF-8
+struct FmtWriter<'a, F: ?Sized> {
+    output: &'a mut F,
+}
+
+impl<'a, F> io::Write for FmtWriter<'a, F>
+where
+    F: ?Sized + fmt::Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let s = std::str::from_utf8(buf)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        self.output.write_str(s).map_err(io::Error::other)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// 按照 `escape` 指定的方式转义字符串并追加到 `output`，不含两侧的引号
+fn format_escaped_str(output: &mut Vec<u8>, value: &str, escape: EscapeMode) {
+    let mut start = 0;
+    for (i, ch) in value.char_indices() {
+        let needs_escape = match ch {
+            '"' | '\\' | '\x00'..='\x1f' => true,
+            '<' | '>' | '&' => escape == EscapeMode::Html,
+            '\u{2028}' | '\u{2029}' => escape != EscapeMode::Minimal,
+            _ => escape == EscapeMode::Ascii && !ch.is_ascii(),
+        };
+        if !needs_escape {
+            continue;
+        }
+        // 未转义的部分整段追加
+        output.extend_from_slice(&value.as_bytes()[start..i]);
+        format_escaped_char(output, ch);
+        start = i + ch.len_utf8();
+    }
+    output.extend_from_slice(&value.as_bytes()[start..]);
+}
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// 以带填充的标准 base64 编码追加到 `output`：每 3 个字节编码为 4 个字符，
+// 最后不足 3 个字节时用 `=` 补齐
+fn format_base64(output: &mut Vec<u8>, bytes: &[u8]) {
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                output.push(BASE64_ALPHABET[(n >> (18 - 6 * i)) as usize & 0x3f]);
+            } else {
+                output.push(b'=');
+            }
+        }
+    }
+}
+
+fn format_hex(output: &mut Vec<u8>, bytes: &[u8]) {
+    for &b in bytes {
+        output.push(HEX_DIGITS[usize::from(b >> 4)]);
+        output.push(HEX_DIGITS[usize::from(b & 0xf)]);
+    }
+}
+
+fn format_escaped_char(output: &mut Vec<u8>, ch: char) {
+    match ch {
+        '"' => output.extend_from_slice(b"\\\""),
+        '\\' => output.extend_from_slice(b"\\\\"),
+        '\x08' => output.extend_from_slice(b"\\b"),
+        '\x0c' => output.extend_from_slice(b"\\f"),
+        '\n' => output.extend_from_slice(b"\\n"),
+        '\r' => output.extend_from_slice(b"\\r"),
+        '\t' => output.extend_from_slice(b"\\t"),
+        _ => {
+            // 其余字符使用 `\uXXXX`，基本多文种平面以外的字符编码为 UTF-16 代理对
+            let mut units = [0; 2];
+            for unit in ch.encode_utf16(&mut units) {
+                output.extend_from_slice(b"\\u");
+                for shift in &[12, 8, 4, 0] {
+                    output.push(HEX_DIGITS[usize::from(*unit >> shift) & 0xf]);
+                }
+            }
+        }
+    }
 }
 
-impl<'a> ser::Serializer for &'a mut Serializer {
+impl<'a, W, F> ser::Serializer for &'a mut Serializer<W, F>
+where
+    W: io::Write,
+    F: Formatter,
+{
     // 成功序列化过程中，此 Serializer产生的输出类型。 
     // 大多数产生文本或二进制输出的序列化程序都应设置 Ok = ()
     // 序列化为 `io::Write` 或 `Serializer` 实例中包含的缓冲区，如此处所示。 通过
@@ -34,20 +363,21 @@ impl<'a> ser::Serializer for &'a mut Serializer {
     type Error = Error;
 
     // 关联类型，用于在序列化复合数据结构（如 `Seq` 和 `Map`）时跟踪附加状态。
-    // 在本例中，除了 Serializer 结构中已存储的状态外，不需要其他任何状态。
-    type SerializeSeq = Self;
-    type SerializeTuple = Self;
-    type SerializeTupleStruct = Self;
-    type SerializeTupleVariant = Self;
-    type SerializeMap = Self;
-    type SerializeStruct = Self;
-    type SerializeStructVariant = Self;
+    // 在本例中，需要额外记录当前是否为第一个元素，以决定是否输出逗号。
+    type SerializeSeq = Compound<'a, W, F>;
+    type SerializeTuple = Compound<'a, W, F>;
+    type SerializeTupleStruct = Compound<'a, W, F>;
+    type SerializeTupleVariant = Compound<'a, W, F>;
+    type SerializeMap = Compound<'a, W, F>;
+    type SerializeStruct = Compound<'a, W, F>;
+    type SerializeStructVariant = Compound<'a, W, F>;
 
     // 这里我们使用简单的方法。以下12种方法之一
     // 数据模型的原始类型，并通过追加将其映射到JSON
     // 放入输出字符串。 
     fn serialize_bool(self, v: bool) -> Result<()> {
-        self.output += if v { "true" } else { "false" };
+        self.buf
+            .extend_from_slice(if v { b"true" } else { b"false" });
         Ok(())
     }
 
@@ -65,9 +395,9 @@ impl<'a> ser::Serializer for &'a mut Serializer {
         self.serialize_i64(i64::from(v))
     }
 
-    // 不是特别有效，但这仍然是示例代码。 一种更高效的方法是使用 `itoa` 库。
+    // `itoa` 在栈上的缓冲区中完成格式化，不需要 `to_string` 分配临时的 `String`
     fn serialize_i64(self, v: i64) -> Result<()> {
-        self.output += &v.to_string();
+        self.buf.extend_from_slice(itoa::Buffer::new().format(v).as_bytes());
         Ok(())
     }
 
@@ -84,40 +414,84 @@ impl<'a> ser::Serializer for &'a mut Serializer {
     }
 
     fn serialize_u64(self, v: u64) -> Result<()> {
-        self.output += &v.to_string();
+        self.buf.extend_from_slice(itoa::Buffer::new().format(v).as_bytes());
+        Ok(())
+    }
+
+    // 128 位整数同样原样输出。JSON 本身不限制数字的大小，但是很多解析器
+    // （例如 JavaScript 的 `JSON.parse`）只能精确表示 2^53 以内的整数
+    fn serialize_i128(self, v: i128) -> Result<()> {
+        self.buf.extend_from_slice(itoa::Buffer::new().format(v).as_bytes());
         Ok(())
     }
 
+    fn serialize_u128(self, v: u128) -> Result<()> {
+        self.buf.extend_from_slice(itoa::Buffer::new().format(v).as_bytes());
+        Ok(())
+    }
+
+    // 按 f32 的精度输出：`0.1f32` 输出为 `0.1`，而不是先转换为 f64 再输出
+    // `0.10000000149011612`
     fn serialize_f32(self, v: f32) -> Result<()> {
-        self.serialize_f64(f64::from(v))
+        if v.is_finite() {
+            self.buf.extend_from_slice(ryu::Buffer::new().format_finite(v).as_bytes());
+            Ok(())
+        } else {
+            self.serialize_non_finite(f64::from(v))
+        }
     }
 
+    // `ryu` 输出能够精确读回的最短形式，并且总是带有小数点或指数，例如
+    // `1.0`、`0.1`、`1e300`，读回时仍然是浮点数。格式化在栈上的缓冲区中
+    // 完成。标准库的 `Display` 会把 `1.0` 输出为 `1`，把 `1e300` 展开为 301
+    // 位的整数
     fn serialize_f64(self, v: f64) -> Result<()> {
-        self.output += &v.to_string();
-        Ok(())
+        if v.is_finite() {
+            self.buf.extend_from_slice(ryu::Buffer::new().format_finite(v).as_bytes());
+            Ok(())
+        } else {
+            self.serialize_non_finite(v)
+        }
     }
 
     // JSON 格式字符直接序列化微字符串即可，其他格式可能需要独立表示
     fn serialize_char(self, v: char) -> Result<()> {
-        self.serialize_str(&v.to_string())
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
     }
 
-    // 简单实现（忽略 `"` 转义）
+    // 按照 `self.escape` 转义后输出
     fn serialize_str(self, v: &str) -> Result<()> {
-        self.output += "\"";
-        self.output += v;
-        self.output += "\"";
+        self.buf.extend_from_slice(b"\"");
+        format_escaped_str(&mut self.buf, v, self.escape);
+        self.buf.extend_from_slice(b"\"");
         Ok(())
     }
 
-    // 再次简单转换为字节数据（当然可以使用Base64格式）
+    // 按照 `self.bytes` 输出为数字数组，或者 base64、十六进制字符串。两种
+    // 编码的输出只包含 ASCII 字母和数字，无需转义
     fn serialize_bytes(self, v: &[u8]) -> Result<()> {
-        use serde::ser::SerializeSeq;
-        let mut seq = self.serialize_seq(Some(v.len()))?;
-        for byte in v {
-            seq.serialize_element(byte)?;
+        match self.bytes {
+            BytesMode::Array => {
+                use serde::ser::SerializeSeq;
+                let mut seq = self.serialize_seq(Some(v.len()))?;
+                for byte in v {
+                    seq.serialize_element(byte)?;
+                }
+                seq.end()
+            }
+            BytesMode::Base64 => {
+                self.buf.push(b'"');
+                format_base64(&mut self.buf, v);
+                self.buf.push(b'"');
+                Ok(())
+            }
+            BytesMode::Hex => {
+                self.buf.push(b'"');
+                format_hex(&mut self.buf, v);
+                self.buf.push(b'"');
+                Ok(())
+            }
         }
-        seq.end()
     }
 
     // None 输出 null
@@ -135,7 +509,7 @@ impl<'a> ser::Serializer for &'a mut Serializer {
 
     // 转换到null
     fn serialize_unit(self) -> Result<()> {
-        self.output += "null";
+        self.buf.extend_from_slice(b"null");
         Ok(())
     }
 
@@ -177,19 +551,18 @@ impl<'a> ser::Serializer for &'a mut Serializer {
     where
         T: ?Sized + Serialize,
     {
-        self.output += "{";
-        variant.serialize(&mut *self)?;
-        self.output += ":";
+        self.begin_variant(variant)?;
         value.serialize(&mut *self)?;
-        self.output += "}";
-        Ok(())
+        self.end_variant()
     }
 
     // 序列化序列，先添加 [
-    // 然后返回实现了 SerializeSeq 的 类型在本例中就是 `Self`
-    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
-        self.output += "[";
-        Ok(self)
+    // 然后返回实现了 SerializeSeq 的 类型在本例中就是 `Compound`
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+        // 每个元素至少占两个字节：值和逗号
+        self.reserve(len, 2);
+        self.formatter.begin_array(&mut self.buf)?;
+        Ok(Compound::new(self))
     }
 
     // 元组作为序列化为JSON数组
@@ -212,18 +585,18 @@ impl<'a> ser::Serializer for &'a mut Serializer {
         _name: &'static str,
         _variant_index: u32,
         variant: &'static str,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        self.output += "{";
-        variant.serialize(&mut *self)?;
-        self.output += ":[";
-        Ok(self)
+        self.begin_variant(variant)?;
+        self.serialize_seq(Some(len))
     }
 
     // 序列化为 map
-    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
-        self.output += "{";
-        Ok(self)
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
+        // 每个键值对至少占五个字节，例如 `"":0,`
+        self.reserve(len, 5);
+        self.formatter.begin_object(&mut self.buf)?;
+        Ok(Compound::new(self))
     }
 
     // Structs look just like maps in JSON. In particular, JSON requires that we
@@ -246,12 +619,130 @@ impl<'a> ser::Serializer for &'a mut Serializer {
         _name: &'static str,
         _variant_index: u32,
         variant: &'static str,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        self.output += "{";
-        variant.serialize(&mut *self)?;
-        self.output += ":{";
-        Ok(self)
+        self.begin_variant(variant)?;
+        self.serialize_map(Some(len))
+    }
+}
+
+impl<W, F> Serializer<W, F>
+where
+    W: io::Write,
+    F: Formatter,
+{
+    // 按照 `self.non_finite` 输出 NaN 或无穷大
+    fn serialize_non_finite(&mut self, v: f64) -> Result<()> {
+        let name = if v.is_nan() {
+            "NaN"
+        } else if v > 0.0 {
+            "Infinity"
+        } else {
+            "-Infinity"
+        };
+        match self.non_finite {
+            NonFinite::Error => Err(ErrorCode::FloatMustBeFinite.into()),
+            NonFinite::Null => {
+                self.buf.extend_from_slice(b"null");
+                Ok(())
+            }
+            NonFinite::String => {
+                self.buf.push(b'"');
+                self.buf.extend_from_slice(name.as_bytes());
+                self.buf.push(b'"');
+                Ok(())
+            }
+        }
+    }
+
+    // 枚举的非 unit 成员都表示为只有一个键的对象 `{ NAME: ... }`，这里输出
+    // 开头的 `{ NAME:`
+    fn begin_variant(&mut self, variant: &'static str) -> Result<()> {
+        self.formatter.begin_object(&mut self.buf)?;
+        self.formatter.begin_object_key(&mut self.buf, true)?;
+        ser::Serializer::serialize_str(&mut *self, variant)?;
+        self.formatter.end_object_key(&mut self.buf)?;
+        self.formatter.begin_object_value(&mut self.buf)?;
+        Ok(())
+    }
+
+    // 输出与 `begin_variant` 对应的结尾 `}`
+    fn end_variant(&mut self) -> Result<()> {
+        self.formatter.end_object_value(&mut self.buf)?;
+        self.formatter.end_object(&mut self.buf)?;
+        Ok(())
+    }
+}
+
+// 复合类型序列化过程中的状态：当前是否为第一个元素
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    First,
+    Rest,
+}
+
+pub struct Compound<'a, W, F> {
+    ser: &'a mut Serializer<W, F>,
+    state: State,
+}
+
+impl<'a, W, F> Compound<'a, W, F>
+where
+    W: io::Write,
+    F: Formatter,
+{
+    fn new(ser: &'a mut Serializer<W, F>) -> Self {
+        Compound {
+            ser,
+            state: State::First,
+        }
+    }
+
+    // 序列化一个数组元素
+    fn serialize_array_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let first = self.state == State::First;
+        self.state = State::Rest;
+        self.ser.formatter.begin_array_value(&mut self.ser.buf, first)?;
+        value.serialize(&mut *self.ser)?;
+        self.ser.formatter.end_array_value(&mut self.ser.buf)?;
+        self.ser.flush_if_full()
+    }
+
+    fn end_array(self) -> Result<()> {
+        self.ser.formatter.end_array(&mut self.ser.buf)?;
+        Ok(())
+    }
+
+    // 序列化一个对象的键
+    fn serialize_object_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let first = self.state == State::First;
+        self.state = State::Rest;
+        self.ser.formatter.begin_object_key(&mut self.ser.buf, first)?;
+        key.serialize(MapKeySerializer { writer: &mut *self.ser })?;
+        self.ser.formatter.end_object_key(&mut self.ser.buf)?;
+        Ok(())
+    }
+
+    // 序列化一个对象的值
+    fn serialize_object_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.ser.formatter.begin_object_value(&mut self.ser.buf)?;
+        value.serialize(&mut *self.ser)?;
+        self.ser.formatter.end_object_value(&mut self.ser.buf)?;
+        self.ser.flush_if_full()
+    }
+
+    fn end_object(self) -> Result<()> {
+        self.ser.formatter.end_object(&mut self.ser.buf)?;
+        Ok(())
     }
 }
 
@@ -262,7 +753,11 @@ impl<'a> ser::Serializer for &'a mut Serializer {
 //
 // 这个暗示是SerializeSeq，因此这些方法在`serialize_seq`之后调用
 // 在序列化程序上被调用。 
-impl<'a> ser::SerializeSeq for &'a mut Serializer {
+impl<'a, W, F> ser::SerializeSeq for Compound<'a, W, F>
+where
+    W: io::Write,
+    F: Formatter,
+{
     // 必须与 Serializer::OK 类型匹配。 
     type Ok = ();
     // 必须与 Serializer::Error 类型匹配
@@ -273,20 +768,20 @@ impl<'a> ser::SerializeSeq for &'a mut Serializer {
     where
         T: ?Sized + Serialize,
     {
-        if !self.output.ends_with('[') {
-            self.output += ",";
-        }
-        value.serialize(&mut **self)
+        self.serialize_array_value(value)
     }
 
     // 完成序列化元素
     fn end(self) -> Result<()> {
-        self.output += "]";
-        Ok(())
+        self.end_array()
     }
 }
 
-impl<'a> ser::SerializeTuple for &'a mut Serializer {
+impl<'a, W, F> ser::SerializeTuple for Compound<'a, W, F>
+where
+    W: io::Write,
+    F: Formatter,
+{
     type Ok = ();
     type Error = Error;
 
@@ -294,19 +789,19 @@ impl<'a> ser::SerializeTuple for &'a mut Serializer {
     where
         T: ?Sized + Serialize,
     {
-        if !self.output.ends_with('[') {
-            self.output += ",";
-        }
-        value.serialize(&mut **self)
+        self.serialize_array_value(value)
     }
 
     fn end(self) -> Result<()> {
-        self.output += "]";
-        Ok(())
+        self.end_array()
     }
 }
 
-impl<'a> ser::SerializeTupleStruct for &'a mut Serializer {
+impl<'a, W, F> ser::SerializeTupleStruct for Compound<'a, W, F>
+where
+    W: io::Write,
+    F: Formatter,
+{
     type Ok = ();
     type Error = Error;
 
@@ -314,25 +809,25 @@ impl<'a> ser::SerializeTupleStruct for &'a mut Serializer {
     where
         T: ?Sized + Serialize,
     {
-        if !self.output.ends_with('[') {
-            self.output += ",";
-        }
-        value.serialize(&mut **self)
+        self.serialize_array_value(value)
     }
 
     fn end(self) -> Result<()> {
-        self.output += "]";
-        Ok(())
+        self.end_array()
     }
 }
 
 // Tuple Variant有些不同。返回参考上面的`serialize_tuple_variant`方法：
 //
-//    self.output += "{";
-//    variant.serialize(&mut *self)?;
-//    self.output += ":[";
+//    self.begin_variant(variant)?;
+//    self.serialize_seq(Some(len))
 //
-impl<'a> ser::SerializeTupleVariant for &'a mut Serializer {
+// 因此 `end` 需要同时关闭数组和外层的对象。
+impl<'a, W, F> ser::SerializeTupleVariant for Compound<'a, W, F>
+where
+    W: io::Write,
+    F: Formatter,
+{
     type Ok = ();
     type Error = Error;
 
@@ -340,15 +835,13 @@ impl<'a> ser::SerializeTupleVariant for &'a mut Serializer {
     where
         T: ?Sized + Serialize,
     {
-        if !self.output.ends_with('[') {
-            self.output += ",";
-        }
-        value.serialize(&mut **self)
+        self.serialize_array_value(value)
     }
 
     fn end(self) -> Result<()> {
-        self.output += "]}";
-        Ok(())
+        let ser = &mut *self.ser;
+        ser.formatter.end_array(&mut ser.buf)?;
+        ser.end_variant()
     }
 }
 
@@ -360,26 +853,23 @@ impl<'a> ser::SerializeTupleVariant for &'a mut Serializer {
 // `serialize_entry` method allows serializers to optimize for the case where
 // key and value are both available simultaneously. In JSON it doesn't make a
 // difference so the default behavior for `serialize_entry` is fine.
-impl<'a> ser::SerializeMap for &'a mut Serializer {
+impl<'a, W, F> ser::SerializeMap for Compound<'a, W, F>
+where
+    W: io::Write,
+    F: Formatter,
+{
     type Ok = ();
     type Error = Error;
 
     // The Serde data model allows map keys to be any serializable type. JSON
-    // only allows string keys so the implementation below will produce invalid
-    // JSON if the key serializes as something other than a string.
-    //
-    // A real JSON serializer would need to validate that map keys are strings.
-    // This can be done by using a different Serializer to serialize the key
-    // (instead of `&mut **self`) and having that other serializer only
-    // implement `serialize_str` and return an error on any other data type.
+    // only allows string keys, so the key goes through `MapKeySerializer`
+    // (instead of `&mut **self`), which quotes integers, booleans and chars and
+    // returns an error on any other data type.
     fn serialize_key<T>(&mut self, key: &T) -> Result<()>
     where
         T: ?Sized + Serialize,
     {
-        if !self.output.ends_with('{') {
-            self.output += ",";
-        }
-        key.serialize(&mut **self)
+        self.serialize_object_key(key)
     }
 
     // It doesn't make a difference whether the colon is printed at the end of
@@ -389,19 +879,21 @@ impl<'a> ser::SerializeMap for &'a mut Serializer {
     where
         T: ?Sized + Serialize,
     {
-        self.output += ":";
-        value.serialize(&mut **self)
+        self.serialize_object_value(value)
     }
 
     fn end(self) -> Result<()> {
-        self.output += "}";
-        Ok(())
+        self.end_object()
     }
 }
 
 // Structs are like maps in which the keys are constrained to be compile-time
 // constant strings.
-impl<'a> ser::SerializeStruct for &'a mut Serializer {
+impl<'a, W, F> ser::SerializeStruct for Compound<'a, W, F>
+where
+    W: io::Write,
+    F: Formatter,
+{
     type Ok = ();
     type Error = Error;
 
@@ -409,23 +901,22 @@ impl<'a> ser::SerializeStruct for &'a mut Serializer {
     where
         T: ?Sized + Serialize,
     {
-        if !self.output.ends_with('{') {
-            self.output += ",";
-        }
-        key.serialize(&mut **self)?;
-        self.output += ":";
-        value.serialize(&mut **self)
+        self.serialize_object_key(key)?;
+        self.serialize_object_value(value)
     }
 
     fn end(self) -> Result<()> {
-        self.output += "}";
-        Ok(())
+        self.end_object()
     }
 }
 
 // Similar to `SerializeTupleVariant`, here the `end` method is responsible for
 // closing both of the curly braces opened by `serialize_struct_variant`.
-impl<'a> ser::SerializeStructVariant for &'a mut Serializer {
+impl<'a, W, F> ser::SerializeStructVariant for Compound<'a, W, F>
+where
+    W: io::Write,
+    F: Formatter,
+{
     type Ok = ();
     type Error = Error;
 
@@ -433,34 +924,858 @@ impl<'a> ser::SerializeStructVariant for &'a mut Serializer {
     where
         T: ?Sized + Serialize,
     {
-        if !self.output.ends_with('{') {
-            self.output += ",";
-        }
-        key.serialize(&mut **self)?;
-        self.output += ":";
-        value.serialize(&mut **self)
+        self.serialize_object_key(key)?;
+        self.serialize_object_value(value)
     }
 
     fn end(self) -> Result<()> {
-        self.output += "}}";
+        let ser = &mut *self.ser;
+        ser.formatter.end_object(&mut ser.buf)?;
+        ser.end_variant()
+    }
+}
+
+// 对象的键只能是字符串
+fn key_must_be_a_string() -> Error {
+    ErrorCode::KeyMustBeAString.into()
+}
+
+// `MapKeySerializer` 确定键的字符串形式后交给 `MapKeyWriter`：文本序列化器
+// 直接写入输出，`to_value` 得到一个 `String`
+pub(crate) trait MapKeyWriter {
+    type Ok;
+
+    // 字符串、字符和 unit variant 的名称，原样作为键
+    fn write_str(self, v: &str) -> Result<Self::Ok>;
+
+    // 整数和布尔值，文本形式加上引号作为键
+    fn write_quoted<T>(self, v: T) -> Result<Self::Ok>
+    where
+        T: Serialize + fmt::Display;
+}
+
+impl<W, F> MapKeyWriter for &mut Serializer<W, F>
+where
+    W: io::Write,
+    F: Formatter,
+{
+    type Ok = ();
+
+    fn write_str(self, v: &str) -> Result<()> {
+        ser::Serializer::serialize_str(self, v)
+    }
+
+    // 按值原本的形式输出，前后加上引号
+    fn write_quoted<T>(self, v: T) -> Result<()>
+    where
+        T: Serialize + fmt::Display,
+    {
+        self.buf.push(b'"');
+        v.serialize(&mut *self)?;
+        self.buf.push(b'"');
         Ok(())
     }
 }
 
-////////////////////////////////////////////////////////////////////////////////
+// 序列化对象的键。字符串和 unit variant 原样输出；整数、布尔值和字符加上
+// 引号，例如 `HashMap<u32, _>` 的键输出为 `"1"`；其余类型（浮点数、None、
+// 数组、结构体等）没有无歧义的字符串形式，返回 `ErrorCode::KeyMustBeAString`。
+// `ch05_04_value_serializer` 同样使用这里的规则
+pub(crate) struct MapKeySerializer<K> {
+    pub(crate) writer: K,
+}
 
-#[test]
-fn test_struct() {
-    #[derive(Serialize)]
-    struct Test {
-        int: u32,
-        seq: Vec<&'static str>,
+impl<K> ser::Serializer for MapKeySerializer<K>
+where
+    K: MapKeyWriter,
+{
+    type Ok = K::Ok;
+    type Error = Error;
+
+    type SerializeSeq = Impossible<K::Ok, Error>;
+    type SerializeTuple = Impossible<K::Ok, Error>;
+    type SerializeTupleStruct = Impossible<K::Ok, Error>;
+    type SerializeTupleVariant = Impossible<K::Ok, Error>;
+    type SerializeMap = Impossible<K::Ok, Error>;
+    type SerializeStruct = Impossible<K::Ok, Error>;
+    type SerializeStructVariant = Impossible<K::Ok, Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<K::Ok> {
+        self.writer.write_quoted(v)
     }
 
-    let test = Test {
-        int: 1,
-        seq: vec!["a", "b"],
-    };
-    let expected = r#"{"int":1,"seq":["a","b"]}"#;
-    assert_eq!(to_string(&test).unwrap(), expected);
+    fn serialize_i8(self, v: i8) -> Result<K::Ok> {
+        self.writer.write_quoted(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<K::Ok> {
+        self.writer.write_quoted(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<K::Ok> {
+        self.writer.write_quoted(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<K::Ok> {
+        self.writer.write_quoted(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<K::Ok> {
+        self.writer.write_quoted(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<K::Ok> {
+        self.writer.write_quoted(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<K::Ok> {
+        self.writer.write_quoted(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<K::Ok> {
+        self.writer.write_quoted(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<K::Ok> {
+        self.writer.write_quoted(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<K::Ok> {
+        self.writer.write_quoted(v)
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<K::Ok> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<K::Ok> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_char(self, v: char) -> Result<K::Ok> {
+        self.writer.write_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<K::Ok> {
+        self.writer.write_str(v)
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<K::Ok> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_none(self) -> Result<K::Ok> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_some<T>(self, _value: &T) -> Result<K::Ok>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit(self) -> Result<K::Ok> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<K::Ok> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<K::Ok> {
+        self.writer.write_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<K::Ok>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<K::Ok>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(key_must_be_a_string())
+    }
+}
+
+// Formatter 决定数组和对象的结构如何输出：JSON 的值本身（数字、字符串等）
+// 由 Serializer 负责，而逗号、冒号、换行和缩进都通过这些钩子写出。
+//
+// 所有方法都有默认实现，默认实现输出紧凑的 JSON，实现者只需覆盖关心的部分
+pub trait Formatter {
+    // 在数组的第一个元素之前调用
+    fn begin_array<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        writer.write_all(b"[")
+    }
+
+    // 在数组的最后一个元素之后调用
+    fn end_array<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        writer.write_all(b"]")
+    }
+
+    // 在每个数组元素之前调用，`first` 表示是否为第一个元素
+    fn begin_array_value<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        if first {
+            Ok(())
+        } else {
+            writer.write_all(b",")
+        }
+    }
+
+    // 在每个数组元素之后调用
+    fn end_array_value<W>(&mut self, _writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        Ok(())
+    }
+
+    // 在对象的第一个键之前调用
+    fn begin_object<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        writer.write_all(b"{")
+    }
+
+    // 在对象的最后一个值之后调用
+    fn end_object<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        writer.write_all(b"}")
+    }
+
+    // 在每个键之前调用，`first` 表示是否为第一个键
+    fn begin_object_key<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        if first {
+            Ok(())
+        } else {
+            writer.write_all(b",")
+        }
+    }
+
+    // 在每个键之后调用
+    fn end_object_key<W>(&mut self, _writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        Ok(())
+    }
+
+    // 在每个值之前调用，负责输出键和值之间的冒号
+    fn begin_object_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        writer.write_all(b":")
+    }
+
+    // 在每个值之后调用
+    fn end_object_value<W>(&mut self, _writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        Ok(())
+    }
+}
+
+// 输出没有任何多余空白的 JSON
+#[derive(Clone, Debug, Default)]
+pub struct CompactFormatter;
+
+impl Formatter for CompactFormatter {}
+
+// 每个元素、每个键值对独占一行，并按嵌套层级缩进
+#[derive(Clone, Debug)]
+pub struct PrettyFormatter<'a> {
+    // 当前的嵌套层级
+    current_indent: usize,
+    // 当前数组或对象是否已经输出过元素，空的 `[]`、`{}` 不换行
+    has_value: bool,
+    // 每一层缩进使用的字符串
+    indent: &'a [u8],
+}
+
+impl<'a> PrettyFormatter<'a> {
+    // 使用两个空格缩进
+    pub fn new() -> Self {
+        PrettyFormatter::with_indent(b"  ")
+    }
+
+    // 使用指定的字符串缩进，例如 `b"\t"` 或 `b"    "`
+    pub fn with_indent(indent: &'a [u8]) -> Self {
+        PrettyFormatter {
+            current_indent: 0,
+            has_value: false,
+            indent,
+        }
+    }
+
+    fn write_indent<W>(&self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        for _ in 0..self.current_indent {
+            writer.write_all(self.indent)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Default for PrettyFormatter<'a> {
+    fn default() -> Self {
+        PrettyFormatter::new()
+    }
+}
+
+impl<'a> Formatter for PrettyFormatter<'a> {
+    fn begin_array<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.current_indent += 1;
+        self.has_value = false;
+        writer.write_all(b"[")
+    }
+
+    fn end_array<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.current_indent -= 1;
+        if self.has_value {
+            writer.write_all(b"\n")?;
+            self.write_indent(writer)?;
+        }
+        writer.write_all(b"]")
+    }
+
+    fn begin_array_value<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        writer.write_all(if first { b"\n" } else { b",\n" })?;
+        self.write_indent(writer)
+    }
+
+    fn end_array_value<W>(&mut self, _writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.has_value = true;
+        Ok(())
+    }
+
+    fn begin_object<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.current_indent += 1;
+        self.has_value = false;
+        writer.write_all(b"{")
+    }
+
+    fn end_object<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.current_indent -= 1;
+        if self.has_value {
+            writer.write_all(b"\n")?;
+            self.write_indent(writer)?;
+        }
+        writer.write_all(b"}")
+    }
+
+    fn begin_object_key<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        writer.write_all(if first { b"\n" } else { b",\n" })?;
+        self.write_indent(writer)
+    }
+
+    fn begin_object_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        writer.write_all(b": ")
+    }
+
+    fn end_object_value<W>(&mut self, _writer: &mut W) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.has_value = true;
+        Ok(())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn test_struct() {
+    #[derive(Serialize)]
+    struct Test {
+        int: u32,
+        seq: Vec<&'static str>,
+    }
+
+    let test = Test {
+        int: 1,
+        seq: vec!["a", "b"],
+    };
+    let expected = r#"{"int":1,"seq":["a","b"]}"#;
+    assert_eq!(to_string(&test).unwrap(), expected);
+}
+
+#[test]
+fn test_escape() {
+    assert_eq!(to_string(&"a\"b\\c").unwrap(), r#""a\"b\\c""#);
+    assert_eq!(
+        to_string(&"\u{8}\u{c}\n\r\t\u{0}\u{1f}").unwrap(),
+        r#""\b\f\n\r\t\u0000\u001f""#
+    );
+    assert_eq!(to_string(&'"').unwrap(), r#""\"""#);
+    // 默认只转义强制要求的字符
+    assert_eq!(to_string(&"é<\u{2028}😀").unwrap(), "\"é<\u{2028}😀\"");
+
+    fn to_string_with<T: Serialize>(value: &T, escape: EscapeMode) -> String {
+        let mut serializer = Serializer::new(Vec::new());
+        serializer.set_escape_mode(escape);
+        value.serialize(&mut serializer).unwrap();
+        String::from_utf8(serializer.into_inner().unwrap()).unwrap()
+    }
+
+    assert_eq!(
+        to_string_with(&"é\"😀 <a>\u{2028}", EscapeMode::Ascii),
+        r#""\u00e9\"\ud83d\ude00 <a>\u2028""#
+    );
+    assert_eq!(
+        to_string_with(&"é</script>&\u{2028}\u{2029}", EscapeMode::Html),
+        r#""é\u003c/script\u003e\u0026\u2028\u2029""#
+    );
+    // 对象的键同样会被转义
+    let mut map = std::collections::BTreeMap::new();
+    map.insert("k\"ey", "vä");
+    assert_eq!(
+        to_string_with(&map, EscapeMode::Ascii),
+        r#"{"k\"ey":"v\u00e4"}"#
+    );
+}
+
+#[test]
+fn test_writer() {
+    use std::collections::BTreeMap;
+    use std::fmt::Write;
+
+    #[derive(Serialize)]
+    struct Test {
+        int: u32,
+        seq: Vec<&'static str>,
+    }
+
+    let test = Test {
+        int: 1,
+        seq: vec!["a", "b"],
+    };
+    let expected = r#"{"int":1,"seq":["a","b"]}"#;
+    assert_eq!(to_vec(&test).unwrap(), expected.as_bytes());
+
+    let mut output = Vec::new();
+    to_writer(&mut output, &test).unwrap();
+    assert_eq!(output, expected.as_bytes());
+
+    let mut output = String::from("test = ");
+    to_fmt(&mut output, &test).unwrap();
+    write!(output, ";").unwrap();
+    assert_eq!(output, format!("test = {};", expected));
+
+    // 输出超过缓冲区大小时分多次写入 writer，结果不变
+    let big: Vec<String> = (0..10_000).map(|i| format!("é{}", i)).collect();
+    let mut output = Vec::new();
+    to_writer(&mut output, &big).unwrap();
+    assert_eq!(output, serde_json::to_vec(&big).unwrap());
+    let mut output = String::new();
+    to_fmt(&mut output, &big).unwrap();
+    assert_eq!(output, serde_json::to_string(&big).unwrap());
+
+    // 序列化中途出错时，缓冲区中不完整的 JSON 不会写入 writer
+    let mut invalid = BTreeMap::new();
+    invalid.insert(vec![1u8], 1);
+    let mut output = Vec::new();
+    assert!(to_writer(&mut output, &invalid).is_err());
+    assert!(output.is_empty());
+}
+
+#[test]
+fn test_writer_error() {
+    struct FailingWriter;
+
+    impl io::Write for FailingWriter {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "pipe closed"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    use super::ch05_00_error::Category;
+
+    let err = to_writer(FailingWriter, &[1, 2, 3]).unwrap_err();
+    match err.code() {
+        ErrorCode::Io(err) => assert_eq!(err.kind(), io::ErrorKind::BrokenPipe),
+        other => panic!("unexpected error: {:?}", other),
+    }
+    assert_eq!(None, err.position());
+    assert_eq!(Category::Io, err.classify());
+}
+
+#[test]
+fn test_pretty() {
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    enum E {
+        Unit,
+        Newtype(u32),
+        Tuple(u32, u32),
+        Struct { a: u32 },
+    }
+
+    #[derive(Serialize)]
+    struct Test {
+        int: u32,
+        seq: Vec<&'static str>,
+        empty_seq: Vec<u32>,
+        empty_map: BTreeMap<String, u32>,
+        e: Vec<E>,
+    }
+
+    let test = Test {
+        int: 1,
+        seq: vec!["a", "b"],
+        empty_seq: vec![],
+        empty_map: BTreeMap::new(),
+        e: vec![E::Unit, E::Newtype(1), E::Tuple(1, 2), E::Struct { a: 1 }],
+    };
+    let expected = r#"{
+  "int": 1,
+  "seq": [
+    "a",
+    "b"
+  ],
+  "empty_seq": [],
+  "empty_map": {},
+  "e": [
+    "Unit",
+    {
+      "Newtype": 1
+    },
+    {
+      "Tuple": [
+        1,
+        2
+      ]
+    },
+    {
+      "Struct": {
+        "a": 1
+      }
+    }
+  ]
+}"#;
+    assert_eq!(to_string_pretty(&test).unwrap(), expected);
+    // 紧凑输出与之前保持一致
+    assert_eq!(
+        to_string(&test).unwrap(),
+        r#"{"int":1,"seq":["a","b"],"empty_seq":[],"empty_map":{},"e":["Unit",{"Newtype":1},{"Tuple":[1,2]},{"Struct":{"a":1}}]}"#
+    );
+
+    let formatter = PrettyFormatter::with_indent(b"\t");
+    let mut serializer = Serializer::with_formatter(Vec::new(), formatter);
+    vec![vec![1], vec![]].serialize(&mut serializer).unwrap();
+    assert_eq!(serializer.into_inner().unwrap(), b"[\n\t[\n\t\t1\n\t],\n\t[]\n]");
+}
+
+#[test]
+fn test_custom_formatter() {
+    // 只在逗号和冒号之后加一个空格
+    struct SpacedFormatter;
+
+    impl Formatter for SpacedFormatter {
+        fn begin_array_value<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+        where
+            W: ?Sized + io::Write,
+        {
+            writer.write_all(if first { b"" } else { b", " })
+        }
+
+        fn begin_object_key<W>(&mut self, writer: &mut W, first: bool) -> io::Result<()>
+        where
+            W: ?Sized + io::Write,
+        {
+            writer.write_all(if first { b"" } else { b", " })
+        }
+
+        fn begin_object_value<W>(&mut self, writer: &mut W) -> io::Result<()>
+        where
+            W: ?Sized + io::Write,
+        {
+            writer.write_all(b": ")
+        }
+    }
+
+    let mut map = std::collections::BTreeMap::new();
+    map.insert("a", vec![1, 2]);
+    map.insert("b", vec![]);
+    let mut serializer = Serializer::with_formatter(Vec::new(), SpacedFormatter);
+    map.serialize(&mut serializer).unwrap();
+    assert_eq!(serializer.into_inner().unwrap(), br#"{"a": [1, 2], "b": []}"#);
+}
+
+#[test]
+fn test_writer_lines() {
+    #[derive(Serialize)]
+    struct Record<'a> {
+        id: u32,
+        msg: &'a str,
+    }
+
+    let records = vec![
+        Record { id: 1, msg: "a\nb" },
+        Record { id: 2, msg: "c" },
+    ];
+    let mut output = Vec::new();
+    to_writer_lines(&mut output, &records).unwrap();
+    let expected = "{\"id\":1,\"msg\":\"a\\nb\"}\n{\"id\":2,\"msg\":\"c\"}\n";
+    assert_eq!(expected, String::from_utf8(output).unwrap());
+
+    let mut output = Vec::new();
+    to_writer_lines(&mut output, Vec::<u32>::new()).unwrap();
+    assert!(output.is_empty());
+}
+
+#[test]
+fn test_bytes() {
+    use serde_bytes::Bytes;
+
+    fn to_string_with(bytes: BytesMode, value: &[u8]) -> String {
+        let mut output = Vec::new();
+        let mut serializer = Serializer::new(&mut output);
+        serializer.set_bytes_mode(bytes);
+        Bytes::new(value).serialize(&mut serializer).unwrap();
+        serializer.flush().unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    assert_eq!("[104,105]", to_string(Bytes::new(b"hi")).unwrap());
+    assert_eq!("[]", to_string_with(BytesMode::Array, b""));
+
+    // RFC 4648 第 10 节的测试向量
+    let cases = [
+        ("", "\"\""),
+        ("f", "\"Zg==\""),
+        ("fo", "\"Zm8=\""),
+        ("foo", "\"Zm9v\""),
+        ("foob", "\"Zm9vYg==\""),
+        ("fooba", "\"Zm9vYmE=\""),
+        ("foobar", "\"Zm9vYmFy\""),
+    ];
+    for &(input, expected) in &cases {
+        assert_eq!(expected, to_string_with(BytesMode::Base64, input.as_bytes()));
+    }
+    assert_eq!("\"+/8=\"", to_string_with(BytesMode::Base64, b"\xfb\xff"));
+
+    assert_eq!("\"00ff10\"", to_string_with(BytesMode::Hex, b"\x00\xff\x10"));
+}
+
+#[test]
+fn test_map_key() {
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize, PartialEq, Eq, PartialOrd, Ord)]
+    enum Kind {
+        A,
+        B(u8),
+    }
+
+    #[derive(Serialize, PartialEq, Eq, PartialOrd, Ord)]
+    struct Id(u32);
+
+    fn map<K: Ord, V>(entries: Vec<(K, V)>) -> BTreeMap<K, V> {
+        entries.into_iter().collect()
+    }
+
+    assert_eq!(r#"{"-1":0,"2":1}"#, to_string(&map(vec![(-1, 0), (2, 1)])).unwrap());
+    assert_eq!(r#"{"false":0,"true":1}"#, to_string(&map(vec![(false, 0), (true, 1)])).unwrap());
+    assert_eq!(r#"{"\"":0}"#, to_string(&map(vec![('"', 0)])).unwrap());
+    assert_eq!(r#"{"A":0}"#, to_string(&map(vec![(Kind::A, 0)])).unwrap());
+    assert_eq!(r#"{"7":0}"#, to_string(&map(vec![(Id(7), 0)])).unwrap());
+
+    let not_strings = [
+        to_string(&map(vec![(Kind::B(1), 0)])),
+        to_string(&map(vec![(Some(1), 0)])),
+        to_string(&map(vec![((), 0)])),
+        to_string(&map(vec![(vec![1], 0)])),
+        to_string(&map(vec![((1, 2), 0)])),
+    ];
+    for result in &not_strings {
+        assert!(matches!(result.as_ref().unwrap_err().code(), ErrorCode::KeyMustBeAString));
+    }
+
+    let mut floats = std::collections::HashMap::new();
+    floats.insert("a", map(vec![(Id(1), 1.5)]));
+    assert_eq!(r#"{"a":{"1":1.5}}"#, to_string(&floats).unwrap());
+}
+
+#[test]
+fn test_float() {
+    use super::ch05_02_deserializer::from_str;
+    use super::ch05_03_value::Value;
+
+    fn to_string_with<T: Serialize>(non_finite: NonFinite, value: T) -> Result<String> {
+        let mut output = Vec::new();
+        let mut serializer = Serializer::new(&mut output);
+        serializer.set_non_finite(non_finite);
+        value.serialize(&mut serializer)?;
+        serializer.flush()?;
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    let cases: &[(f64, &str)] = &[
+        (1.0, "1.0"),
+        (-0.0, "-0.0"),
+        (0.1, "0.1"),
+        (1.5e300, "1.5e300"),
+        (1e-7, "1e-7"),
+        (123456.789, "123456.789"),
+        (f64::MAX, "1.7976931348623157e308"),
+        (f64::MIN_POSITIVE, "2.2250738585072014e-308"),
+        (5e-324, "5e-324"),
+    ];
+    for &(value, expected) in cases {
+        let text = to_string(&value).unwrap();
+        assert_eq!(expected, text);
+        // 精确读回，并且读回的仍然是浮点数
+        assert_eq!(value.to_bits(), from_str::<f64>(&text).unwrap().to_bits());
+        assert!(from_str::<Value>(&text).unwrap().as_number().unwrap().is_f64());
+    }
+
+    assert_eq!("0.1", to_string(&0.1f32).unwrap());
+    assert_eq!("3.4028235e38", to_string(&f32::MAX).unwrap());
+    assert_eq!("16777216.0", to_string(&16_777_216f32).unwrap());
+    assert_eq!(0.1f32, from_str::<f32>(&to_string(&0.1f32).unwrap()).unwrap());
+
+    assert_eq!("[null,null]", to_string(&[f64::NAN, f64::INFINITY]).unwrap());
+    let err = to_string_with(NonFinite::Error, f64::NEG_INFINITY).unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::FloatMustBeFinite));
+    assert!(to_string_with(NonFinite::Error, f32::NAN).is_err());
+    let strings = to_string_with(NonFinite::String, (f64::NAN, f64::INFINITY, f32::NEG_INFINITY));
+    assert_eq!(r#"["NaN","Infinity","-Infinity"]"#, strings.unwrap());
+    assert_eq!("1.0", to_string_with(NonFinite::Error, 1.0).unwrap());
+}
+
+#[test]
+fn test_integer() {
+    let cases: &[(i64, &str)] = &[
+        (0, "0"),
+        (7, "7"),
+        (10, "10"),
+        (-99, "-99"),
+        (100, "100"),
+        (-1234567, "-1234567"),
+        (i64::MAX, "9223372036854775807"),
+        (i64::MIN, "-9223372036854775808"),
+    ];
+    for &(value, expected) in cases {
+        assert_eq!(expected, to_string(&value).unwrap());
+    }
+    assert_eq!("18446744073709551615", to_string(&u64::MAX).unwrap());
+    let small = (255u8, i8::MIN, u16::MAX, i32::MIN);
+    assert_eq!("[255,-128,65535,-2147483648]", to_string(&small).unwrap());
+    assert_eq!("340282366920938463463374607431768211455", to_string(&u128::MAX).unwrap());
+    assert_eq!("-170141183460469231731687303715884105728", to_string(&i128::MIN).unwrap());
+    assert_eq!("-1", to_string(&-1i128).unwrap());
+    // 与标准库的格式化结果一致
+    for n in (0..64).map(|shift| 1u64 << shift).chain(0..1000) {
+        for &n in &[n, n.wrapping_sub(1)] {
+            assert_eq!(n.to_string(), to_string(&n).unwrap());
+            assert_eq!((n as i64).to_string(), to_string(&(n as i64)).unwrap());
+        }
+    }
 }
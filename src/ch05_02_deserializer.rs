@@ -1,36 +1,201 @@
-use std::ops::{AddAssign, MulAssign, Neg};
-
-use serde::Deserialize;
+use std::any::type_name;
+use std::convert::TryFrom;
+use std::io;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::str::{self, FromStr};
+
+use serde::{forward_to_deserialize_any, Deserialize};
 use serde::de::{
-    self, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess,
-    VariantAccess, Visitor,
+    self, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess,
+    SeqAccess, VariantAccess, Visitor,
 };
 
-use super::ch05_00_error::{Error, Result};
+use super::ch05_00_error::{Error, ErrorCode, Position, Result};
+use super::ch05_01_serializer::BytesMode;
+
+pub struct Deserializer<R> {
+    // 反序列化器，从 `Read` 中逐字节读取输入
+    read: R,
+    // 含有转义的字符串（以及读取器中的所有字符串）无法直接借用输入，
+    // 解码后的内容暂存在这里
+    scratch: Vec<u8>,
+    // 正在解析的数字的文本
+    number: String,
+    // 字节数组以字符串形式出现时的编码
+    bytes: BytesMode,
+    // 还允许进入的数组、对象和枚举的层数，None 表示不限制
+    remaining_depth: Option<usize>,
+    limits: Limits,
+    number_mode: NumberMode,
+}
 
+// 数字的语法。默认是 `Lenient`，与早先的解析器接受的数字相同
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NumberMode {
+    // 严格按照 RFC 8259：不允许多余的前导零（`007`）、`+` 号、省略整数或
+    // 小数部分的数字（`.5`、`1.`）以及十六进制（`0x1F`）
+    Strict,
+    // 兼容不规范的生产者：允许多余的前导零（`007`、`-01`），其余与
+    // `Strict` 相同
+    #[default]
+    Lenient,
+}
 
-pub struct Deserializer<'de> {
-    // 反序列化器，持有字符串切片
-    input: &'de str,
+// 反序列化器的资源限制，防止不可信的输入耗尽内存。None 表示不限制，默认
+// 全部不限制。嵌套深度的限制见 `Deserializer::set_recursion_limit`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Limits {
+    // 单个字符串（包括对象的键）解码后的最大字节数
+    pub max_string_len: Option<usize>,
+    // 单个数字的文本（包括符号、小数点和指数）的最大字节数。数字的文本会
+    // 全部缓存在内存中，不限制时一长串数字同样可以耗尽内存
+    pub max_number_len: Option<usize>,
+    // 单个数组或对象的最大元素个数
+    pub max_elements: Option<usize>,
+    // 最多读取的输入字节数。读取器最多从底层读取这么多字节，超出的部分
+    // 不会被读入内存
+    pub max_input_len: Option<usize>,
+}
+
+// 默认的最大嵌套深度
+const DEFAULT_RECURSION_LIMIT: usize = 128;
+
+impl<'de, R: Read<'de>> Deserializer<R> {
+    pub fn new(read: R) -> Self {
+        Deserializer {
+            read,
+            scratch: Vec::new(),
+            number: String::new(),
+            bytes: BytesMode::default(),
+            remaining_depth: Some(DEFAULT_RECURSION_LIMIT),
+            limits: Limits::default(),
+            number_mode: NumberMode::default(),
+        }
+    }
+
+    // 设置字节数组的编码，应与序列化时 `Serializer::set_bytes_mode` 的设置
+    // 一致。数字数组形式的字节数组在任何设置下都能读取
+    pub fn set_bytes_mode(&mut self, bytes: BytesMode) {
+        self.bytes = bytes;
+    }
+
+    // 设置数组、对象和枚举的最大嵌套深度，默认为 128
+    pub fn set_recursion_limit(&mut self, limit: usize) {
+        self.remaining_depth = Some(limit);
+    }
+
+    // 取消嵌套深度限制。只应对可信的输入这样做：解析是递归的，足够深的
+    // 嵌套会耗尽调用栈，使整个进程中止
+    pub fn disable_recursion_limit(&mut self) {
+        self.remaining_depth = None;
+    }
+
+    // 设置数字的语法，默认为 `NumberMode::Lenient`。`from_str`、`from_slice`
+    // 和 `from_reader` 总是使用默认设置，需要 `NumberMode::Strict` 时自己
+    // 创建反序列化器
+    pub fn set_number_mode(&mut self, number_mode: NumberMode) {
+        self.number_mode = number_mode;
+    }
+
+    // 设置资源限制。超出限制时分别返回 `ErrorCode::StringTooLong`、
+    // `ErrorCode::NumberTooLong`、`ErrorCode::TooManyElements` 和
+    // `ErrorCode::InputTooLong`
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.read.set_max_input_len(limits.max_input_len.unwrap_or(usize::MAX));
+        self.limits = limits;
+    }
 }
 
-impl<'de> Deserializer<'de> {
+impl<'de> Deserializer<StrRead<'de>> {
     pub fn from_str(input: &'de str) -> Self {
-        Deserializer { input }
+        Deserializer::new(StrRead::new(input))
+    }
+}
+
+impl<'de> Deserializer<SliceRead<'de>> {
+    pub fn from_slice(input: &'de [u8]) -> Self {
+        Deserializer::new(SliceRead::new(input))
+    }
+}
+
+impl<R: io::Read> Deserializer<IoRead<R>> {
+    pub fn from_reader(reader: R) -> Self {
+        Deserializer::new(IoRead::new(reader))
+    }
+}
+
+// `parse_string` 的结果：不含转义的字符串直接借用输入（生命周期 'b），
+// 含有转义的字符串借用解码后的 scratch 缓冲区（生命周期 'c）
+pub enum Reference<'b, 'c> {
+    Borrowed(&'b str),
+    Copied(&'c str),
+}
+
+impl<'b, 'c> Deref for Reference<'b, 'c> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match *self {
+            Reference::Borrowed(s) => s,
+            Reference::Copied(s) => s,
+        }
     }
 }
 
 // 公开API
+//
+// 反序列化不会 panic：任何输入（非法的 UTF-8、截断、超出范围的数字、深层
+// 嵌套等）都以 `Err` 返回。前提是目标类型的 `Deserialize` 实现和
+// `io::Read` 本身不 panic，并且没有取消嵌套深度限制。`fuzz/` 目录下的
+// 模糊测试检查这一点
 pub fn from_str<'a, T>(s: &'a str) -> Result<T>
 where
     T: Deserialize<'a>,
 {
-    let mut deserializer = Deserializer::from_str(s);
-    let t = T::deserialize(&mut deserializer)?;
-    if deserializer.input.is_empty() {
-        Ok(t)
-    } else {
-        Err(Error::TrailingCharacters)
+    from_trait(StrRead::new(s))
+}
+
+// 从字节切片反序列化。UTF-8 只在字符串内部检查，不含转义的字符串仍然
+// 直接借用输入
+pub fn from_slice<'a, T>(v: &'a [u8]) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    from_trait(SliceRead::new(v))
+}
+
+// 从 `io::Read` 反序列化，输入经由内部缓冲区逐块读取。
+//
+// 读取器的内容无法借用，所有字符串都以 `visit_str` / `visit_string` 交给
+// visitor，因此 T 必须是 `DeserializeOwned`
+pub fn from_reader<R, T>(reader: R) -> Result<T>
+where
+    R: io::Read,
+    T: DeserializeOwned,
+{
+    from_trait(IoRead::new(reader))
+}
+
+fn from_trait<'de, R, T>(read: R) -> Result<T>
+where
+    R: Read<'de>,
+    T: Deserialize<'de>,
+{
+    let mut deserializer = Deserializer::new(read);
+    let t = T::deserialize(&mut deserializer).map_err(|err| deserializer.fix_position(err))?;
+    deserializer.end()?;
+    Ok(t)
+}
+
+impl<'de, R: Read<'de>> Deserializer<R> {
+    // 检查值之后只剩下空白字符
+    pub fn end(&mut self) -> Result<()> {
+        self.parse_whitespace()?;
+        match self.read.peek()? {
+            None => Ok(()),
+            Some(_) => Err(self.peek_error(ErrorCode::TrailingCharacters)),
+        }
     }
 }
 
@@ -39,88 +204,497 @@ where
 // parsing library to help implement their Serde deserializer.
 
 // Serde 不是解析库。该impl块定义了一些基本解析函数
-// 较复杂的格式不妨使用专用的解析库以帮助实现其Serde反序列化器。 
-impl<'de> Deserializer<'de> {
-    // 读取第一个字符
-    fn peek_char(&mut self) -> Result<char> {
-        self.input.chars().next().ok_or(Error::Eof)
+// 较复杂的格式不妨使用专用的解析库以帮助实现其Serde反序列化器。
+impl<'de, R: Read<'de>> Deserializer<R> {
+    // 当前读取到的位置
+    fn position(&self) -> Position {
+        self.read.position()
+    }
+
+    // 创建一个位于当前位置的错误
+    fn error(&self, code: ErrorCode) -> Error {
+        self.read.error(code)
+    }
+
+    // 创建一个位于当前位置的错误，并记录该位置上实际遇到的字符。输入已经
+    // 结束时报告 `ErrorCode::Eof`
+    fn peek_error(&mut self, code: ErrorCode) -> Error {
+        match self.read.peek() {
+            Ok(Some(b)) if b.is_ascii() => self.error(code).with_found(char::from(b)),
+            // 读取器无法回看完整的 UTF-8 序列，非 ASCII 字节统一记为替换字符
+            Ok(Some(_)) => self.error(code).with_found(char::REPLACEMENT_CHARACTER),
+            Ok(None) => self.error(ErrorCode::Eof),
+            Err(err) => err,
+        }
+    }
+
+    // `de::Error::custom` 创建的错误（例如缺少字段）没有位置，用当前位置补上
+    fn fix_position(&self, err: Error) -> Error {
+        err.fix_position(|| self.position())
+    }
+
+    // 进入一层数组、对象或枚举，在其中调用 `f`。超过嵌套深度限制时返回
+    // `ErrorCode::RecursionLimitExceeded`，错误指向刚刚消费的开括号之后
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        if let Some(remaining) = self.remaining_depth {
+            if remaining == 0 {
+                return Err(self.error(ErrorCode::RecursionLimitExceeded));
+            }
+            self.remaining_depth = Some(remaining - 1);
+        }
+        let result = f(self);
+        if let Some(remaining) = &mut self.remaining_depth {
+            *remaining += 1;
+        }
+        result
+    }
+
+    // 跳过 RFC 8259 允许的空白字符：空格、制表符、换行和回车
+    fn parse_whitespace(&mut self) -> Result<()> {
+        self.read.skip_whitespace().map(drop)
     }
 
-    // 消费第一个字符（指针向后移动一个）
-    fn next_char(&mut self) -> Result<char> {
-        let ch = self.peek_char()?;
-        self.input = &self.input[ch.len_utf8()..];
-        Ok(ch)
+    // 跳过空白字符后读取第一个字节
+    fn peek_token(&mut self) -> Result<u8> {
+        self.read.skip_whitespace()?.ok_or_else(|| self.error(ErrorCode::Eof))
+    }
+
+    // 跳过空白字符后消费预期的字节。不匹配时不消费，错误指向该字节的位置
+    fn expect_token(&mut self, expected: u8, code: ErrorCode) -> Result<()> {
+        if self.peek_token()? == expected {
+            self.read.discard();
+            Ok(())
+        } else {
+            Err(self.peek_error(code))
+        }
+    }
+
+    // 数组或对象中读取下一个元素之前调用：跳过空白后遇到 `end` 时返回 false
+    // 且不消费；不是第一个元素时必须遇到逗号，消费逗号后返回 true。只跳过
+    // 一次空白就能区分结尾、逗号和语法错误，错误指向不符合预期的字节
+    fn has_next_element(&mut self, first: bool, end: u8, code: ErrorCode) -> Result<bool> {
+        match self.peek_token()? {
+            b if b == end => Ok(false),
+            b',' if !first => {
+                self.read.discard();
+                Ok(true)
+            }
+            _ if first => Ok(true),
+            _ => Err(self.peek_error(code)),
+        }
+    }
+
+    // 消费 `true`、`false`、`null` 这样的字面量，不匹配时返回 `code`
+    fn parse_ident(&mut self, ident: &[u8], code: ErrorCode) -> Result<()> {
+        for &expected in ident {
+            if self.read.peek()? != Some(expected) {
+                return Err(self.peek_error(code));
+            }
+            self.read.discard();
+        }
+        Ok(())
     }
 
     // 解析 Bool 类型数据
     fn parse_bool(&mut self) -> Result<bool> {
-        if self.input.starts_with("true") {
-            self.input = &self.input["true".len()..];
-            Ok(true)
-        } else if self.input.starts_with("false") {
-            self.input = &self.input["false".len()..];
-            Ok(false)
-        } else {
-            Err(Error::ExpectedBoolean)
+        match self.peek_token()? {
+            b't' => {
+                self.parse_ident(b"true", ErrorCode::ExpectedBoolean)?;
+                Ok(true)
+            }
+            b'f' => {
+                self.parse_ident(b"false", ErrorCode::ExpectedBoolean)?;
+                Ok(false)
+            }
+            _ => Err(self.peek_error(ErrorCode::ExpectedBoolean)),
         }
     }
 
-    //将一组十进制数字解析为类型T的无符号整数。 
+    // 按照 RFC 8259 的数字语法扫描一个数字：
     //
-    // 仅简单实现，没有考虑复杂边界条件
-    fn parse_unsigned<T>(&mut self) -> Result<T>
-    where
-        T: AddAssign<T> + MulAssign<T> + From<u8>,
-    {
-        let mut int = match self.next_char()? {
-            ch @ '0'..='9' => T::from(ch as u8 - b'0'),
-            _ => {
-                return Err(Error::ExpectedInteger);
+    //     number = [ minus ] int [ frac ] [ exp ]
+    //     int    = zero / ( digit1-9 *DIGIT )
+    //
+    // `NumberMode::Lenient` 只放宽 `int` 中多余的前导零。数字的文本存入
+    // `self.number`，返回它是否包含小数或指数部分。输入不以数字开头时返回
+    // `expected`，以数字开头但不符合语法时返回 `ErrorCode::InvalidNumber`，
+    // 错误指向第一个不符合语法的字节
+    fn scan_number(&mut self, expected: ErrorCode) -> Result<bool> {
+        self.number.clear();
+        let lenient = self.number_mode == NumberMode::Lenient;
+        let negative = match self.peek_token()? {
+            b'-' => {
+                self.read.discard();
+                self.number.push('-');
+                true
             }
+            b'+' => return Err(self.peek_error(ErrorCode::InvalidNumber)),
+            _ => false,
         };
-        loop {
-            match self.input.chars().next() {
-                Some(ch @ '0'..='9') => {
-                    self.input = &self.input[1..];
-                    int *= T::from(10);
-                    int += T::from(ch as u8 - b'0');
-                }
-                _ => {
-                    return Ok(int);
+        // 整数部分
+        match self.read.peek()? {
+            Some(b'0') if !lenient => {
+                self.read.discard();
+                self.number.push('0');
+                match self.read.peek()? {
+                    // 多余的前导零，例如 `007`
+                    Some(b'0'..=b'9') => return Err(self.peek_error(ErrorCode::InvalidNumber)),
+                    // 十六进制，例如 `0x1F`
+                    Some(b'x') | Some(b'X') => {
+                        return Err(self.peek_error(ErrorCode::InvalidNumber))
+                    }
+                    _ => {}
                 }
             }
+            Some(b'0'..=b'9') => {
+                self.scan_digits()?;
+            }
+            Some(b'.') => return Err(self.peek_error(ErrorCode::InvalidNumber)),
+            _ if negative => return Err(self.error(ErrorCode::InvalidNumber)),
+            _ => return Err(self.peek_error(expected)),
+        }
+        let mut is_float = false;
+        if self.read.peek()? == Some(b'.') {
+            is_float = true;
+            self.read.discard();
+            self.number.push('.');
+            if self.scan_digits()? == 0 {
+                return Err(self.error(ErrorCode::InvalidNumber));
+            }
+        }
+        if let Some(b'e') | Some(b'E') = self.read.peek()? {
+            is_float = true;
+            self.read.discard();
+            self.number.push('e');
+            if let Some(sign @ b'+') | Some(sign @ b'-') = self.read.peek()? {
+                self.read.discard();
+                self.number.push(char::from(sign));
+            }
+            if self.scan_digits()? == 0 {
+                return Err(self.error(ErrorCode::InvalidNumber));
+            }
+        }
+        Ok(is_float)
+    }
+
+    // 把连续的十进制数字追加到 `self.number`，返回数字的个数。数字的文本
+    // 超过 `Limits::max_number_len` 时错误指向第一个超出的数字
+    fn scan_digits(&mut self) -> Result<usize> {
+        let max_len = self.limits.max_number_len.unwrap_or(usize::MAX);
+        let mut count = 0;
+        while let Some(digit @ b'0'..=b'9') = self.read.peek()? {
+            if self.number.len() >= max_len {
+                return Err(self.peek_error(ErrorCode::NumberTooLong));
+            }
+            self.read.discard();
+            self.number.push(char::from(digit));
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    // 解析无符号整型，超出类型 T 的范围时返回 `ErrorCode::NumberOutOfRange`
+    fn parse_unsigned<T>(&mut self) -> Result<T>
+    where
+        T: TryFrom<u64>,
+    {
+        if self.scan_number(ErrorCode::ExpectedInteger)? {
+            return Err(self.error(ErrorCode::ExpectedInteger));
         }
+        let int = match self.number.strip_prefix('-') {
+            // 除了 -0 以外，负数不在任何无符号整型的范围内
+            Some(digits) => parse_u64(digits).filter(|&int| int == 0),
+            None => parse_u64(&self.number),
+        };
+        int.and_then(|int| T::try_from(int).ok())
+            .ok_or_else(out_of_range::<T>)
     }
 
-    // 解析有符号整型
+    // 解析有符号整型，超出类型 T 的范围时返回 `ErrorCode::NumberOutOfRange`
     fn parse_signed<T>(&mut self) -> Result<T>
     where
-        T: Neg<Output = T> + AddAssign<T> + MulAssign<T> + From<i8>,
+        T: TryFrom<i64>,
+    {
+        if self.scan_number(ErrorCode::ExpectedInteger)? {
+            return Err(self.error(ErrorCode::ExpectedInteger));
+        }
+        parse_i64(&self.number)
+            .and_then(|int| T::try_from(int).ok())
+            .ok_or_else(out_of_range::<T>)
+    }
+
+    // 与 `parse_unsigned` 相同，只是按 128 位计算。64 位以内的整数仍然走
+    // `parse_unsigned`，以免每个数字都做 128 位的乘法
+    fn parse_u128(&mut self) -> Result<u128> {
+        if self.scan_number(ErrorCode::ExpectedInteger)? {
+            return Err(self.error(ErrorCode::ExpectedInteger));
+        }
+        let int = match self.number.strip_prefix('-') {
+            Some(digits) => parse_u128_digits(digits).filter(|&int| int == 0),
+            None => parse_u128_digits(&self.number),
+        };
+        int.ok_or_else(out_of_range::<u128>)
+    }
+
+    fn parse_i128(&mut self) -> Result<i128> {
+        if self.scan_number(ErrorCode::ExpectedInteger)? {
+            return Err(self.error(ErrorCode::ExpectedInteger));
+        }
+        parse_i128_number(&self.number).ok_or_else(out_of_range::<i128>)
+    }
+
+    // 解析浮点数，整数形式的数字同样接受。溢出为无穷大时返回
+    // `ErrorCode::NumberOutOfRange`
+    fn parse_float<T>(&mut self, is_finite: fn(T) -> bool) -> Result<T>
+    where
+        T: FromStr + Copy,
+    {
+        self.scan_number(ErrorCode::ExpectedFloat)?;
+        parse_float(&self.number, is_finite)
+    }
+
+    // `deserialize_any` 遇到数字时根据其形式选择 visitor 方法：含小数或指数的
+    // 按 f64 处理，负整数按 i64 处理，其余按 u64 处理。超出 i64 / u64 范围的
+    // 整数交给 `visit_i128` / `visit_u128`，超出 128 位时才报错
+    fn deserialize_number<V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
     {
-        // 解析到负号并委托到 parse_unsigned
-        unimplemented!()
+        let is_float = self.scan_number(ErrorCode::Syntax)?;
+        let number = self.number.as_str();
+        if is_float {
+            visitor.visit_f64(parse_float(number, f64::is_finite)?)
+        } else if number.starts_with('-') {
+            match parse_i64(number) {
+                Some(int) => visitor.visit_i64(int),
+                None => {
+                    let int = parse_i128_number(number).ok_or_else(out_of_range::<i128>)?;
+                    visitor.visit_i128(int)
+                }
+            }
+        } else {
+            match parse_u64(number) {
+                Some(int) => visitor.visit_u64(int),
+                None => {
+                    let int = parse_u128_digits(number).ok_or_else(out_of_range::<u128>)?;
+                    visitor.visit_u128(int)
+                }
+            }
+        }
     }
 
-    // 解析一个字符串（直到遇到"为止）
+    // 解析一个字符串（直到遇到未转义的"为止）
     //
-    // 忽略转移
-    fn parse_string(&mut self) -> Result<&'de str> {
-        if self.next_char()? != '"' {
-            return Err(Error::ExpectedString);
+    // 能否借用输入由 `Read` 决定：字符串切片和字节切片中不含转义的字符串
+    // 直接借用输入，其余情况返回对 scratch 的引用
+    fn parse_string(&mut self) -> Result<Reference<'de, '_>> {
+        self.expect_token(b'"', ErrorCode::ExpectedString)?;
+        self.scratch.clear();
+        let max_len = self.limits.max_string_len.unwrap_or(usize::MAX);
+        self.read.parse_str(&mut self.scratch, max_len)
+    }
+
+    // 读取一个 base64 或十六进制编码的字节字符串。解码失败时错误指向字符串
+    // 开头的引号
+    fn parse_encoded_bytes(
+        &mut self,
+        decode: fn(&[u8]) -> Option<Vec<u8>>,
+        code: ErrorCode,
+    ) -> Result<Vec<u8>> {
+        self.peek_token()?;
+        let position = self.position();
+        let decoded = decode(self.parse_string()?.as_bytes());
+        decoded.ok_or_else(|| Error::new(code, Some(position)))
+    }
+
+    // 数组或对象中第 `len` 个元素开始解析时调用，个数超出
+    // `Limits::max_elements` 时错误指向这个元素
+    fn check_element_count(&mut self, len: usize) -> Result<()> {
+        match self.limits.max_elements {
+            Some(max) if len > max => {
+                self.peek_token()?;
+                Err(self.error(ErrorCode::TooManyElements))
+            }
+            _ => Ok(()),
         }
-        match self.input.find('"') {
-            Some(len) => {
-                let s = &self.input[..len];
-                self.input = &self.input[len + 1..];
-                Ok(s)
+    }
+
+    // 跳过一个值，供 `deserialize_ignored_any` 使用。
+    //
+    // 只匹配括号、引号并检查语法，不调用 visitor，数字也不转换为整数或浮点数。
+    // 语法检查与正常解析使用同样的函数，因此跳过时接受和拒绝的输入与解析为
+    // `deserialize_any` 时相同，错误的种类和位置也相同。例外是超出范围的
+    // 数字：它们的语法正确，跳过时不会报错
+    fn ignore_value(&mut self) -> Result<()> {
+        match self.peek_token()? {
+            b'n' => self.parse_ident(b"null", ErrorCode::ExpectedNull),
+            b't' | b'f' => self.parse_bool().map(drop),
+            b'"' => {
+                self.read.discard();
+                self.ignore_str()
+            }
+            b'0'..=b'9' | b'-' | b'+' | b'.' => self.scan_number(ErrorCode::Syntax).map(drop),
+            b'[' => {
+                self.read.discard();
+                self.nested(Self::ignore_seq)?;
+                self.expect_token(b']', ErrorCode::ExpectedArrayEnd)
             }
-            None => Err(Error::Eof),
+            b'{' => {
+                self.read.discard();
+                self.nested(Self::ignore_map)?;
+                self.expect_token(b'}', ErrorCode::ExpectedMapEnd)
+            }
+            _ => Err(self.peek_error(ErrorCode::Syntax)),
+        }
+    }
+
+    // 开头的引号已经消费。字符串只检查不保留，但仍然经过 `Read::parse_str`：
+    // `SliceRead` 和 `StrRead` 中不含转义的字符串不复制，含有转义时解码到
+    // scratch 中；`IoRead` 总是把内容复制到 scratch 中。scratch 在多次调用之间
+    // 复用，只有遇到比之前都长的字符串时才需要扩容
+    fn ignore_str(&mut self) -> Result<()> {
+        self.scratch.clear();
+        let max_len = self.limits.max_string_len.unwrap_or(usize::MAX);
+        self.read.parse_str(&mut self.scratch, max_len).map(drop)
+    }
+
+    // 跳过数组的元素，不消费结尾的 `]`
+    fn ignore_seq(&mut self) -> Result<()> {
+        let mut len = 0;
+        while self.has_next_element(len == 0, b']', ErrorCode::ExpectedArrayComma)? {
+            len += 1;
+            self.check_element_count(len)?;
+            self.ignore_value()?;
+        }
+        Ok(())
+    }
+
+    // 跳过对象的键值对，不消费结尾的 `}`
+    fn ignore_map(&mut self) -> Result<()> {
+        let mut len = 0;
+        while self.has_next_element(len == 0, b'}', ErrorCode::ExpectedMapComma)? {
+            len += 1;
+            self.check_element_count(len)?;
+            self.expect_token(b'"', ErrorCode::ExpectedString)?;
+            self.ignore_str()?;
+            self.expect_token(b':', ErrorCode::ExpectedMapColon)?;
+            self.ignore_value()?;
+        }
+        Ok(())
+    }
+}
+
+fn base64_value(c: u8) -> Option<u32> {
+    let value = match c {
+        b'A'..=b'Z' => c - b'A',
+        b'a'..=b'z' => c - b'a' + 26,
+        b'0'..=b'9' => c - b'0' + 52,
+        b'+' => 62,
+        b'/' => 63,
+        _ => return None,
+    };
+    Some(u32::from(value))
+}
+
+// 解码带填充的标准 base64。长度必须是 4 的倍数，`=` 只能出现在最后一组的
+// 末尾，最多两个。
+//
+// `usize::is_multiple_of` 要求 Rust 1.87，这里和 `decode_hex` 都用取余
+#[allow(clippy::manual_is_multiple_of)]
+fn decode_base64(input: &[u8]) -> Option<Vec<u8>> {
+    if input.len() % 4 != 0 {
+        return None;
+    }
+    let groups = input.len() / 4;
+    let mut output = Vec::with_capacity(groups * 3);
+    for (i, chunk) in input.chunks(4).enumerate() {
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 || (padding > 0 && i + 1 < groups) {
+            return None;
         }
+        let mut n = 0;
+        for &c in &chunk[..4 - padding] {
+            n = n << 6 | base64_value(c)?;
+        }
+        n <<= 6 * padding;
+        output.extend_from_slice(&n.to_be_bytes()[1..4 - padding]);
+    }
+    Some(output)
+}
+
+// 解码十六进制字符串，大小写均可
+#[allow(clippy::manual_is_multiple_of)]
+fn decode_hex(input: &[u8]) -> Option<Vec<u8>> {
+    if input.len() % 2 != 0 {
+        return None;
+    }
+    input
+        .chunks(2)
+        .map(|pair| {
+            let high = char::from(pair[0]).to_digit(16)?;
+            let low = char::from(pair[1]).to_digit(16)?;
+            Some((high << 4 | low) as u8)
+        })
+        .collect()
+}
+
+// 数字超出目标类型 T 的范围
+//
+// 这里不知道位置，由 `from_str` 在错误传出时补上
+fn out_of_range<T>() -> Error {
+    ErrorCode::NumberOutOfRange(type_name::<T>()).into()
+}
+
+// 将一组十进制数字转换为 u64，超出 u64 范围时返回 None
+fn parse_u64(digits: &str) -> Option<u64> {
+    digits.bytes().try_fold(0u64, |int, digit| {
+        int.checked_mul(10)?.checked_add(u64::from(digit - b'0'))
+    })
+}
+
+// 将 `scan_number` 扫描到的整数转换为 i64，超出 i64 范围时返回 None
+fn parse_i64(number: &str) -> Option<i64> {
+    match number.strip_prefix('-') {
+        // i64::MIN 的绝对值比 i64::MAX 大 1，因此不能先转换为 i64 再取反
+        Some(digits) => parse_u64(digits)
+            .filter(|&int| int <= i64::MIN.unsigned_abs())
+            .map(|int| (int as i64).wrapping_neg()),
+        None => parse_u64(number).and_then(|int| i64::try_from(int).ok()),
+    }
+}
+
+// 与 `parse_u64` 和 `parse_i64` 相同，只是按 128 位计算
+fn parse_u128_digits(digits: &str) -> Option<u128> {
+    digits.bytes().try_fold(0u128, |int, digit| {
+        int.checked_mul(10)?.checked_add(u128::from(digit - b'0'))
+    })
+}
+
+fn parse_i128_number(number: &str) -> Option<i128> {
+    match number.strip_prefix('-') {
+        Some(digits) => parse_u128_digits(digits)
+            .filter(|&int| int <= i128::MIN.unsigned_abs())
+            .map(|int| (int as i128).wrapping_neg()),
+        None => parse_u128_digits(number).and_then(|int| i128::try_from(int).ok()),
+    }
+}
+
+// 将 `scan_number` 扫描到的数字转换为浮点数。
+//
+// 浮点解析非常困难，好在标准库的 `FromStr` 实现保证得到最接近的可表示值。
+// f32 直接按 f32 精度解析，而不是先解析为 f64 再转换，以免两次舍入
+fn parse_float<T>(number: &str, is_finite: fn(T) -> bool) -> Result<T>
+where
+    T: FromStr + Copy,
+{
+    match number.parse() {
+        Ok(float) if is_finite(float) => Ok(float),
+        // 语法已经检查过，这里只可能是溢出为无穷大
+        _ => Err(out_of_range::<T>()),
     }
 }
 
-impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
+impl<'de, R: Read<'de>> de::Deserializer<'de> for &mut Deserializer<R> {
     type Error = Error;
 
     // 查看输入数据以决定要使用哪种Serde数据模型类型
@@ -130,15 +704,16 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        match self.peek_char()? {
-            'n' => self.deserialize_unit(visitor),
-            't' | 'f' => self.deserialize_bool(visitor),
-            '"' => self.deserialize_str(visitor),
-            '0'..='9' => self.deserialize_u64(visitor),
-            '-' => self.deserialize_i64(visitor),
-            '[' => self.deserialize_seq(visitor),
-            '{' => self.deserialize_map(visitor),
-            _ => Err(Error::Syntax),
+        match self.peek_token()? {
+            b'n' => self.deserialize_unit(visitor),
+            b't' | b'f' => self.deserialize_bool(visitor),
+            b'"' => self.deserialize_str(visitor),
+            // `+` 和 `.` 开头的数字不合法，交给 `scan_number` 报告
+            // `ErrorCode::InvalidNumber`
+            b'0'..=b'9' | b'-' | b'+' | b'.' => self.deserialize_number(visitor),
+            b'[' => self.deserialize_seq(visitor),
+            b'{' => self.deserialize_map(visitor),
+            _ => Err(self.peek_error(ErrorCode::Syntax)),
         }
     }
 
@@ -208,58 +783,102 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         visitor.visit_u64(self.parse_unsigned()?)
     }
 
-    // 浮点解析非常困难。 
-    fn deserialize_f32<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_i128(self.parse_i128()?)
+    }
+
+    fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_u128(self.parse_u128()?)
+    }
+
+    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        visitor.visit_f32(self.parse_float(f32::is_finite)?)
     }
 
-    // 浮点解析非常困难。 
-    fn deserialize_f64<V>(self, _visitor: V) -> Result<V::Value>
+    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        visitor.visit_f64(self.parse_float(f64::is_finite)?)
     }
 
-    fn deserialize_char<V>(self, _visitor: V) -> Result<V::Value>
+    // JSON 没有字符类型，字符表示为只含一个字符的字符串。由 visitor 检查
+    // 字符串的长度
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        self.deserialize_str(visitor)
     }
 
     // https://serde.rs/lifetimes.html
+    //
+    // 只有不含转义的字符串才能以 `&'de str` 的形式借出。含有转义的字符串
+    // 通过 `visit_str` 传递，若 visitor 只接受借用的字符串（例如 `&'de str`
+    // 字段），它会报告 "expected a borrowed string"
     fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_borrowed_str(self.parse_string()?)
+        match self.parse_string()? {
+            Reference::Borrowed(s) => visitor.visit_borrowed_str(s),
+            Reference::Copied(s) => visitor.visit_str(s),
+        }
     }
 
+    // 含有转义的字符串复制一份交给调用方。scratch 的分配留给下一个字符串
+    // 复用，不随结果交出去
     fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        self.deserialize_str(visitor)
+        match self.parse_string()? {
+            Reference::Borrowed(s) => visitor.visit_borrowed_str(s),
+            Reference::Copied(s) => visitor.visit_string(s.to_owned()),
+        }
     }
 
     // The `Serializer` implementation on the previous page serialized byte
     // arrays as JSON arrays of bytes. Handle that representation here.
-    fn deserialize_bytes<V>(self, _visitor: V) -> Result<V::Value>
+    //
+    // 数字数组和解码后的字符串都不是输入的一部分，无法借用
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        self.deserialize_byte_buf(visitor)
     }
 
-    fn deserialize_byte_buf<V>(self, _visitor: V) -> Result<V::Value>
+    // 数字数组的每个元素都必须在 u8 的范围内。字符串按 `self.bytes` 解码，
+    // `BytesMode::Array` 下原样交给 visitor，由它决定是否接受
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        unimplemented!()
+        match self.peek_token()? {
+            b'[' => visitor.visit_byte_buf(Vec::<u8>::deserialize(&mut *self)?),
+            b'"' => {
+                let bytes = match self.bytes {
+                    BytesMode::Array => return self.deserialize_str(visitor),
+                    BytesMode::Base64 => {
+                        self.parse_encoded_bytes(decode_base64, ErrorCode::InvalidBase64)?
+                    }
+                    BytesMode::Hex => self.parse_encoded_bytes(decode_hex, ErrorCode::InvalidHex)?,
+                };
+                visitor.visit_byte_buf(bytes)
+            }
+            _ if self.bytes == BytesMode::Array => Err(self.peek_error(ErrorCode::ExpectedArray)),
+            _ => Err(self.peek_error(ErrorCode::ExpectedString)),
+        }
     }
 
     // An absent optional is represented as the JSON `null` and a present
@@ -274,8 +893,8 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        if self.input.starts_with("null") {
-            self.input = &self.input["null".len()..];
+        if self.peek_token()? == b'n' {
+            self.parse_ident(b"null", ErrorCode::ExpectedNull)?;
             visitor.visit_none()
         } else {
             visitor.visit_some(self)
@@ -287,11 +906,11 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        if self.input.starts_with("null") {
-            self.input = &self.input["null".len()..];
+        if self.peek_token()? == b'n' {
+            self.parse_ident(b"null", ErrorCode::ExpectedNull)?;
             visitor.visit_unit()
         } else {
-            Err(Error::ExpectedNull)
+            Err(self.peek_error(ErrorCode::ExpectedNull))
         }
     }
 
@@ -324,23 +943,17 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     // Deserialization of compound types like sequences and maps happens by
     // passing the visitor an "Access" object that gives it the ability to
     // iterate through the data contained in the sequence.
-    fn deserialize_seq<V>(mut self, visitor: V) -> Result<V::Value>
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
         // Parse the opening bracket of the sequence.
-        if self.next_char()? == '[' {
-            // Give the visitor access to each element of the sequence.
-            let value = visitor.visit_seq(CommaSeparated::new(&mut self))?;
-            // Parse the closing bracket of the sequence.
-            if self.next_char()? == ']' {
-                Ok(value)
-            } else {
-                Err(Error::ExpectedArrayEnd)
-            }
-        } else {
-            Err(Error::ExpectedArray)
-        }
+        self.expect_token(b'[', ErrorCode::ExpectedArray)?;
+        // Give the visitor access to each element of the sequence.
+        let value = self.nested(|de| visitor.visit_seq(CommaSeparated::new(de)))?;
+        // Parse the closing bracket of the sequence.
+        self.expect_token(b']', ErrorCode::ExpectedArrayEnd)?;
+        Ok(value)
     }
 
     // Tuples look just like sequences in JSON. Some formats may be able to
@@ -372,23 +985,17 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     // Much like `deserialize_seq` but calls the visitors `visit_map` method
     // with a `MapAccess` implementation, rather than the visitor's `visit_seq`
     // method with a `SeqAccess` implementation.
-    fn deserialize_map<V>(mut self, visitor: V) -> Result<V::Value>
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
         // Parse the opening brace of the map.
-        if self.next_char()? == '{' {
-            // Give the visitor access to each entry of the map.
-            let value = visitor.visit_map(CommaSeparated::new(&mut self))?;
-            // Parse the closing brace of the map.
-            if self.next_char()? == '}' {
-                Ok(value)
-            } else {
-                Err(Error::ExpectedMapEnd)
-            }
-        } else {
-            Err(Error::ExpectedMap)
-        }
+        self.expect_token(b'{', ErrorCode::ExpectedMap)?;
+        // Give the visitor access to each entry of the map.
+        let value = self.nested(|de| visitor.visit_map(CommaSeparated::new(de)))?;
+        // Parse the closing brace of the map.
+        self.expect_token(b'}', ErrorCode::ExpectedMapEnd)?;
+        Ok(value)
     }
 
     // Structs look just like maps in JSON.
@@ -418,20 +1025,18 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        if self.peek_char()? == '"' {
+        match self.peek_token()? {
             // Visit a unit variant.
-            visitor.visit_enum(self.parse_string()?.into_deserializer())
-        } else if self.next_char()? == '{' {
-            // Visit a newtype variant, tuple variant, or struct variant.
-            let value = visitor.visit_enum(Enum::new(self))?;
-            // Parse the matching close brace.
-            if self.next_char()? == '}' {
+            b'"' => visitor.visit_enum((*self.parse_string()?).into_deserializer()),
+            b'{' => {
+                self.read.discard();
+                // Visit a newtype variant, tuple variant, or struct variant.
+                let value = self.nested(|de| visitor.visit_enum(Enum::new(de)))?;
+                // Parse the matching close brace.
+                self.expect_token(b'}', ErrorCode::ExpectedMapEnd)?;
                 Ok(value)
-            } else {
-                Err(Error::ExpectedMapEnd)
             }
-        } else {
-            Err(Error::ExpectedEnum)
+            _ => Err(self.peek_error(ErrorCode::ExpectedEnum)),
         }
     }
 
@@ -457,49 +1062,60 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     // Some formats are not able to implement this at all. Formats that can
     // implement `deserialize_any` and `deserialize_ignored_any` are known as
     // self-describing.
+    //
+    // 结构体中未知的字段由 derive 生成的代码以 `IgnoredAny` 读取，会自动走到
+    // 这里，见 `ignore_value`
     fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        self.deserialize_any(visitor)
+        self.ignore_value()?;
+        visitor.visit_unit()
     }
 }
 
 // In order to handle commas correctly when deserializing a JSON array or map,
 // we need to track whether we are on the first element or past the first
 // element.
-struct CommaSeparated<'a, 'de: 'a> {
-    de: &'a mut Deserializer<'de>,
+struct CommaSeparated<'a, R: 'a> {
+    de: &'a mut Deserializer<R>,
     first: bool,
+    // 已经开始解析的元素个数，用于检查 `Limits::max_elements`
+    len: usize,
 }
 
-impl<'a, 'de> CommaSeparated<'a, 'de> {
-    fn new(de: &'a mut Deserializer<'de>) -> Self {
+impl<'a, 'de, R: Read<'de> + 'a> CommaSeparated<'a, R> {
+    fn new(de: &'a mut Deserializer<R>) -> Self {
         CommaSeparated {
             de,
             first: true,
+            len: 0,
         }
     }
+
+    // 开始解析下一个元素，元素个数超出限制时错误指向这个元素
+    fn count_element(&mut self) -> Result<()> {
+        self.len += 1;
+        self.de.check_element_count(self.len)
+    }
 }
 
 // `SeqAccess` is provided to the `Visitor` to give it the ability to iterate
 // through elements of the sequence.
-impl<'de, 'a> SeqAccess<'de> for CommaSeparated<'a, 'de> {
+impl<'de, 'a, R: Read<'de> + 'a> SeqAccess<'de> for CommaSeparated<'a, R> {
     type Error = Error;
 
     fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
     where
         T: DeserializeSeed<'de>,
     {
-        // Check if there are no more elements.
-        if self.de.peek_char()? == ']' {
+        // Check if there are no more elements. Comma is required before every
+        // element except the first.
+        if !self.de.has_next_element(self.first, b']', ErrorCode::ExpectedArrayComma)? {
             return Ok(None);
         }
-        // Comma is required before every element except the first.
-        if !self.first && self.de.next_char()? != ',' {
-            return Err(Error::ExpectedArrayComma);
-        }
         self.first = false;
+        self.count_element()?;
         // Deserialize an array element.
         seed.deserialize(&mut *self.de).map(Some)
     }
@@ -507,24 +1123,22 @@ impl<'de, 'a> SeqAccess<'de> for CommaSeparated<'a, 'de> {
 
 // `MapAccess` is provided to the `Visitor` to give it the ability to iterate
 // through entries of the map.
-impl<'de, 'a> MapAccess<'de> for CommaSeparated<'a, 'de> {
+impl<'de, 'a, R: Read<'de> + 'a> MapAccess<'de> for CommaSeparated<'a, R> {
     type Error = Error;
 
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
     where
         K: DeserializeSeed<'de>,
     {
-        // Check if there are no more entries.
-        if self.de.peek_char()? == '}' {
+        // Check if there are no more entries. Comma is required before every
+        // entry except the first.
+        if !self.de.has_next_element(self.first, b'}', ErrorCode::ExpectedMapComma)? {
             return Ok(None);
         }
-        // Comma is required before every entry except the first.
-        if !self.first && self.de.next_char()? != ',' {
-            return Err(Error::ExpectedMapComma);
-        }
         self.first = false;
+        self.count_element()?;
         // Deserialize a map key.
-        seed.deserialize(&mut *self.de).map(Some)
+        seed.deserialize(MapKey { de: &mut *self.de }).map(Some)
     }
 
     fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
@@ -534,82 +1148,164 @@ impl<'de, 'a> MapAccess<'de> for CommaSeparated<'a, 'de> {
         // It doesn't make a difference whether the colon is parsed at the end
         // of `next_key_seed` or at the beginning of `next_value_seed`. In this
         // case the code is a bit simpler having it here.
-        if self.de.next_char()? != ':' {
-            return Err(Error::ExpectedMapColon);
-        }
+        self.de.expect_token(b':', ErrorCode::ExpectedMapColon)?;
         // Deserialize a map value.
         seed.deserialize(&mut *self.de)
     }
 }
 
-struct Enum<'a, 'de: 'a> {
-    de: &'a mut Deserializer<'de>,
+// 对象的键总是字符串。整数和布尔值类型的键（例如 `HashMap<u32, _>`）在
+// 序列化时加上了引号，这里去掉引号后按原本的类型解析，其余类型按字符串处理
+struct MapKey<'a, R: 'a> {
+    de: &'a mut Deserializer<R>,
 }
 
-impl<'a, 'de> Enum<'a, 'de> {
-    fn new(de: &'a mut Deserializer<'de>) -> Self {
-        Enum { de }
-    }
+// 引号内必须紧跟着整数或布尔值的字面量，之后紧跟着结尾的引号，不允许空白
+macro_rules! deserialize_quoted_key {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                self.de.expect_token(b'"', ErrorCode::ExpectedString)?;
+                match self.de.read.peek()? {
+                    Some(b'-') | Some(b'0'..=b'9') | Some(b't') | Some(b'f') => {}
+                    _ => return Err(self.de.peek_error(ErrorCode::InvalidMapKey)),
+                }
+                let value = de::Deserializer::$method(&mut *self.de, visitor)?;
+                if self.de.read.peek()? != Some(b'"') {
+                    return Err(self.de.peek_error(ErrorCode::InvalidMapKey));
+                }
+                self.de.read.discard();
+                Ok(value)
+            }
+        )*
+    };
 }
 
-// `EnumAccess` is provided to the `Visitor` to give it the ability to determine
-// which variant of the enum is supposed to be deserialized.
-//
-// Note that all enum deserialization methods in Serde refer exclusively to the
-// "externally tagged" enum representation.
-impl<'de, 'a> EnumAccess<'de> for Enum<'a, 'de> {
+impl<'de, 'a, R: Read<'de> + 'a> de::Deserializer<'de> for MapKey<'a, R> {
     type Error = Error;
-    type Variant = Self;
 
-    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
     where
-        V: DeserializeSeed<'de>,
+        V: Visitor<'de>,
     {
-        // The `deserialize_enum` method parsed a `{` character so we are
-        // currently inside of a map. The seed will be deserializing itself from
-        // the key of the map.
-        let val = seed.deserialize(&mut *self.de)?;
-        // Parse the colon separating map key from value.
-        if self.de.next_char()? == ':' {
-            Ok((val, self))
-        } else {
-            Err(Error::ExpectedMapColon)
-        }
+        de::Deserializer::deserialize_str(self.de, visitor)
     }
-}
-
-// `VariantAccess` is provided to the `Visitor` to give it the ability to see
-// the content of the single variant that it decided to deserialize.
-impl<'de, 'a> VariantAccess<'de> for Enum<'a, 'de> {
-    type Error = Error;
 
-    // If the `Visitor` expected this variant to be a unit variant, the input
-    // should have been the plain string case handled in `deserialize_enum`.
-    fn unit_variant(self) -> Result<()> {
-        Err(Error::ExpectedString)
+    deserialize_quoted_key! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_i128 deserialize_u128
     }
 
-    // Newtype variants are represented in JSON as `{ NAME: VALUE }` so
-    // deserialize the value here.
-    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
     where
-        T: DeserializeSeed<'de>,
+        V: Visitor<'de>,
     {
-        seed.deserialize(self.de)
+        de::Deserializer::deserialize_string(self.de, visitor)
     }
 
-    // Tuple variants are represented in JSON as `{ NAME: [DATA...] }` so
-    // deserialize the sequence of data here.
-    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    // 键不可能是 null
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        de::Deserializer::deserialize_seq(self.de, visitor)
+        visitor.visit_some(self)
     }
 
-    // Struct variants are represented in JSON as `{ NAME: { K: V, ... } }` so
-    // deserialize the inner map here.
-    fn struct_variant<V>(
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // unit variant 作为键
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_enum(self.de, name, variants, visitor)
+    }
+
+    forward_to_deserialize_any! {
+        f32 f64 char str bytes byte_buf unit unit_struct seq tuple tuple_struct
+        map struct identifier ignored_any
+    }
+}
+
+struct Enum<'a, R: 'a> {
+    de: &'a mut Deserializer<R>,
+}
+
+impl<'a, R: 'a> Enum<'a, R> {
+    fn new(de: &'a mut Deserializer<R>) -> Self {
+        Enum { de }
+    }
+}
+
+// `EnumAccess` is provided to the `Visitor` to give it the ability to determine
+// which variant of the enum is supposed to be deserialized.
+//
+// Note that all enum deserialization methods in Serde refer exclusively to the
+// "externally tagged" enum representation.
+impl<'de, 'a, R: Read<'de> + 'a> EnumAccess<'de> for Enum<'a, R> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        // The `deserialize_enum` method parsed a `{` character so we are
+        // currently inside of a map. The seed will be deserializing itself from
+        // the key of the map.
+        let val = seed.deserialize(&mut *self.de)?;
+        // Parse the colon separating map key from value.
+        self.de.expect_token(b':', ErrorCode::ExpectedMapColon)?;
+        Ok((val, self))
+    }
+}
+
+// `VariantAccess` is provided to the `Visitor` to give it the ability to see
+// the content of the single variant that it decided to deserialize.
+impl<'de, 'a, R: Read<'de> + 'a> VariantAccess<'de> for Enum<'a, R> {
+    type Error = Error;
+
+    // If the `Visitor` expected this variant to be a unit variant, the input
+    // should have been the plain string case handled in `deserialize_enum`.
+    fn unit_variant(self) -> Result<()> {
+        Err(self.de.error(ErrorCode::ExpectedString))
+    }
+
+    // Newtype variants are represented in JSON as `{ NAME: VALUE }` so
+    // deserialize the value here.
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        seed.deserialize(self.de)
+    }
+
+    // Tuple variants are represented in JSON as `{ NAME: [DATA...] }` so
+    // deserialize the sequence of data here.
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_seq(self.de, visitor)
+    }
+
+    // Struct variants are represented in JSON as `{ NAME: { K: V, ... } }` so
+    // deserialize the inner map here.
+    fn struct_variant<V>(
         self,
         _fields: &'static [&'static str],
         visitor: V,
@@ -621,6 +1317,673 @@ impl<'de, 'a> VariantAccess<'de> for Enum<'a, 'de> {
     }
 }
 
+// 依次反序列化输入中的多个值，例如每行一个值的日志文件（NDJSON）。
+//
+// 值之间可以用空白字符分隔。数字、`true` 这类没有结束标记的值之后必须是
+// 空白字符或输入结束；对象、数组和字符串则可以首尾相连，例如 `{}{}`。
+// 遇到错误后迭代结束
+pub struct StreamDeserializer<'de, R, T> {
+    de: Deserializer<R>,
+    // 最近一个成功反序列化的值之后的字节偏移
+    offset: usize,
+    failed: bool,
+    output: PhantomData<T>,
+    lifetime: PhantomData<&'de ()>,
+}
+
+impl<'de, R, T> StreamDeserializer<'de, R, T>
+where
+    R: Read<'de>,
+    T: Deserialize<'de>,
+{
+    // 使用默认设置的反序列化器
+    pub fn new(read: R) -> Self {
+        StreamDeserializer::from_deserializer(Deserializer::new(read))
+    }
+
+    // 使用已经设置好的反序列化器，例如设置了 `Limits`、嵌套深度或
+    // `NumberMode`。设置对每个值都生效，其中 `Limits::max_input_len` 限制的
+    // 是整个输入的长度
+    pub fn from_deserializer(de: Deserializer<R>) -> Self {
+        StreamDeserializer {
+            offset: de.read.byte_offset(),
+            de,
+            failed: false,
+            output: PhantomData,
+            lifetime: PhantomData,
+        }
+    }
+
+    // 最近一个成功反序列化的值之后的字节偏移。出错时可以据此跳过或者
+    // 报告出错的记录
+    pub fn byte_offset(&self) -> usize {
+        self.offset
+    }
+
+    fn next_value(&mut self) -> Result<Option<T>> {
+        self.de.parse_whitespace()?;
+        let self_delimiting = match self.de.read.peek()? {
+            None => return Ok(None),
+            Some(b) => matches!(b, b'{' | b'[' | b'"'),
+        };
+        let value = T::deserialize(&mut self.de).map_err(|err| self.de.fix_position(err))?;
+        if !self_delimiting {
+            match self.de.read.peek()? {
+                Some(b) if !is_whitespace(b) => {
+                    return Err(self.de.peek_error(ErrorCode::TrailingCharacters))
+                }
+                _ => {}
+            }
+        }
+        self.offset = self.de.read.byte_offset();
+        Ok(Some(value))
+    }
+}
+
+impl<'de, R, T> Iterator for StreamDeserializer<'de, R, T>
+where
+    R: Read<'de>,
+    T: Deserialize<'de>,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        if self.failed {
+            return None;
+        }
+        let result = self.next_value().transpose();
+        self.failed = matches!(result, Some(Err(_)));
+        result
+    }
+}
+
+// 反序列化器的输入来源。解析器只通过这个 trait 逐字节读取输入，字符串的
+// 扫描交给各个实现，以便切片输入能够直接借用。
+//
+// 这个 trait 是封闭的，只有本模块中的 `StrRead`、`SliceRead` 和 `IoRead`
+// 实现了它
+pub trait Read<'de>: private::Sealed {
+    // 查看下一个字节但不消费，输入结束时返回 None
+    fn peek(&mut self) -> Result<Option<u8>>;
+
+    // 消费并返回下一个字节，输入结束时返回 None
+    fn next(&mut self) -> Result<Option<u8>>;
+
+    // 消费刚刚 `peek` 到的字节
+    fn discard(&mut self);
+
+    // 跳过空白字符，返回之后的第一个字节但不消费，输入结束时返回 None。
+    // 默认逐字节跳过
+    fn skip_whitespace(&mut self) -> Result<Option<u8>> {
+        loop {
+            match self.peek()? {
+                Some(b) if is_whitespace(b) => self.discard(),
+                other => return Ok(other),
+            }
+        }
+    }
+
+    // 下一个未读字节的位置
+    fn position(&self) -> Position;
+
+    // 已消费的字节数。与 `position` 不同，它总是可以廉价地得到
+    fn byte_offset(&self) -> usize;
+
+    // 开头的引号已经消费，解析字符串余下的部分（包括结尾的引号）。
+    // 调用方保证 scratch 为空。解码后超过 `max_len` 字节时返回
+    // `ErrorCode::StringTooLong`
+    fn parse_str<'s>(
+        &'s mut self,
+        scratch: &'s mut Vec<u8>,
+        max_len: usize,
+    ) -> Result<Reference<'de, 's>>;
+
+    // 最多读取 `limit` 个字节，之后读取时返回 `ErrorCode::InputTooLong`
+    fn set_max_input_len(&mut self, limit: usize);
+
+    // 创建一个位于当前位置的错误
+    fn error(&self, code: ErrorCode) -> Error {
+        Error::new(code, Some(self.position()))
+    }
+}
+
+mod private {
+    pub trait Sealed {}
+}
+
+// 字符串中需要特殊处理的字节：结尾的引号、转义和必须转义的控制字符
+fn is_special(b: u8) -> bool {
+    b == b'"' || b == b'\\' || b < 0x20
+}
+
+// RFC 8259 允许的空白字符：空格、制表符、换行和回车
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\n' | b'\r')
+}
+
+const ONES: u64 = 0x0101_0101_0101_0101;
+const HIGH_BITS: u64 = 0x8080_8080_8080_8080;
+const LOW_BITS: u64 = 0x7F7F_7F7F_7F7F_7F7F;
+
+// 标记 word 中小于 n（n <= 0x80）的字节：对应字节的最高位置 1。减法的借位
+// 只会从低位字节向高位字节传播，因此只有最低的标记一定准确
+fn bytes_less_than(word: u64, n: u8) -> u64 {
+    word.wrapping_sub(ONES * u64::from(n)) & !word & HIGH_BITS
+}
+
+fn bytes_equal_to(word: u64, b: u8) -> u64 {
+    bytes_less_than(word ^ (ONES * u64::from(b)), 1)
+}
+
+// 标记 word 中不等于 b 的字节。每个字节的低 7 位单独相加，字节之间没有
+// 进位，所有标记都准确。查找第一个不匹配的字节时，之前的字节都不能有误报
+fn bytes_not_equal_to(word: u64, b: u8) -> u64 {
+    let x = word ^ (ONES * u64::from(b));
+    (((x & LOW_BITS) + LOW_BITS) | x) & HIGH_BITS
+}
+
+// 每次按小端序读取 8 个字节，`mask` 标记其中要找的字节，最低的标记就是第一
+// 个匹配的字节，因此 `mask` 只需保证最低的标记准确。不足 8 个字节的尾部用
+// `matches` 逐字节查找。
+//
+// 解析大文档时逐字节比较是瓶颈：字符串的内容和带缩进的输出中的空白都可能
+// 很长。这里只用到整数运算，不需要 unsafe 的 SIMD 指令，在所有平台上行为
+// 一致
+#[inline]
+fn find_word_at_a_time(
+    bytes: &[u8],
+    mask: impl Fn(u64) -> u64,
+    matches: impl Fn(u8) -> bool,
+) -> Option<usize> {
+    let mut chunks = bytes.chunks_exact(8);
+    let mut offset = 0;
+    for chunk in &mut chunks {
+        let mut word = [0; 8];
+        word.copy_from_slice(chunk);
+        let mask = mask(u64::from_le_bytes(word));
+        if mask != 0 {
+            return Some(offset + mask.trailing_zeros() as usize / 8);
+        }
+        offset += 8;
+    }
+    let rest = chunks.remainder();
+    rest.iter().position(|&b| matches(b)).map(|len| offset + len)
+}
+
+// 查找第一个 `is_special` 的字节。三个条件各自最低的标记都是准确的，它们的
+// 并集中最低的标记就是第一个特殊字节
+fn find_special(bytes: &[u8]) -> Option<usize> {
+    find_word_at_a_time(
+        bytes,
+        |word| {
+            bytes_less_than(word, 0x20) | bytes_equal_to(word, b'"') | bytes_equal_to(word, b'\\')
+        },
+        is_special,
+    )
+}
+
+// 开头连续的空白字符的个数。只在确实遇到空白时调用，不内联可以让
+// `SliceRead::skip_whitespace` 的快速路径保持短小
+#[inline(never)]
+fn whitespace_len(bytes: &[u8]) -> usize {
+    find_word_at_a_time(
+        bytes,
+        |word| {
+            bytes_not_equal_to(word, b' ')
+                & bytes_not_equal_to(word, b'\t')
+                & bytes_not_equal_to(word, b'\n')
+                & bytes_not_equal_to(word, b'\r')
+        },
+        |b| !is_whitespace(b),
+    )
+    .unwrap_or(bytes.len())
+}
+
+fn check_str_len<'de, R: Read<'de>>(read: &R, len: usize, max_len: usize) -> Result<()> {
+    if len > max_len {
+        Err(read.error(ErrorCode::StringTooLong))
+    } else {
+        Ok(())
+    }
+}
+
+// 含有转义的字符串解码完毕，检查 scratch 是否是合法的 UTF-8
+fn copied<'de, 's, R: Read<'de>>(read: &R, scratch: &'s [u8]) -> Result<Reference<'de, 's>> {
+    match str::from_utf8(scratch) {
+        Ok(s) => Ok(Reference::Copied(s)),
+        Err(_) => Err(read.error(ErrorCode::InvalidUtf8)),
+    }
+}
+
+fn next_or_eof<'de, R: Read<'de>>(read: &mut R) -> Result<u8> {
+    read.next()?.ok_or_else(|| read.error(ErrorCode::Eof))
+}
+
+// 解析 `\` 之后的转义序列，并把解码得到的字符以 UTF-8 写入 scratch
+fn parse_escape<'de, R: Read<'de>>(read: &mut R, scratch: &mut Vec<u8>) -> Result<()> {
+    let ch = match next_or_eof(read)? {
+        b'"' => '"',
+        b'\\' => '\\',
+        b'/' => '/',
+        b'b' => '\x08',
+        b'f' => '\x0c',
+        b'n' => '\n',
+        b'r' => '\r',
+        b't' => '\t',
+        b'u' => parse_unicode_escape(read)?,
+        _ => return Err(read.error(ErrorCode::InvalidEscape)),
+    };
+    scratch.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes());
+    Ok(())
+}
+
+// 解析 `\u` 之后的 4 位十六进制数。基本多文种平面以外的字符以 UTF-16
+// 代理对的形式出现，即两个连续的 `\uXXXX`
+fn parse_unicode_escape<'de, R: Read<'de>>(read: &mut R) -> Result<char> {
+    let n = match decode_hex_escape(read)? {
+        n @ 0xD800..=0xDBFF => {
+            // 高位代理之后必须紧跟一个低位代理
+            if read.peek()? != Some(b'\\') {
+                return Err(read.error(ErrorCode::LoneSurrogate));
+            }
+            read.discard();
+            if read.peek()? != Some(b'u') {
+                return Err(read.error(ErrorCode::LoneSurrogate));
+            }
+            read.discard();
+            let n2 = decode_hex_escape(read)?;
+            if !(0xDC00..=0xDFFF).contains(&n2) {
+                return Err(read.error(ErrorCode::LoneSurrogate));
+            }
+            0x10000 + ((u32::from(n) - 0xD800) << 10) + (u32::from(n2) - 0xDC00)
+        }
+        0xDC00..=0xDFFF => return Err(read.error(ErrorCode::LoneSurrogate)),
+        n => u32::from(n),
+    };
+    // 代理区间已经排除，剩下的码点都是合法的 char
+    std::char::from_u32(n).ok_or_else(|| read.error(ErrorCode::LoneSurrogate))
+}
+
+fn decode_hex_escape<'de, R: Read<'de>>(read: &mut R) -> Result<u16> {
+    let mut n = 0;
+    for _ in 0..4 {
+        let digit = char::from(next_or_eof(read)?)
+            .to_digit(16)
+            .ok_or_else(|| read.error(ErrorCode::InvalidUnicodeEscape))?;
+        n = n * 16 + digit as u16;
+    }
+    Ok(n)
+}
+
+// 从字节切片读取。UTF-8 只在字符串内部检查
+pub struct SliceRead<'de> {
+    slice: &'de [u8],
+    // 下一个未读字节的下标
+    index: usize,
+    // 允许读取的范围是 `slice[..limit]`
+    limit: usize,
+}
+
+impl<'de> SliceRead<'de> {
+    pub fn new(slice: &'de [u8]) -> Self {
+        SliceRead {
+            slice,
+            index: 0,
+            limit: slice.len(),
+        }
+    }
+
+    // 读到 `limit` 时：后面还有输入则是超出了限制，否则是输入结束
+    fn end_error(&self) -> Error {
+        if self.limit < self.slice.len() {
+            self.error(ErrorCode::InputTooLong)
+        } else {
+            self.error(ErrorCode::Eof)
+        }
+    }
+
+    fn position_of(&self, offset: usize) -> Position {
+        Position::from_offset(self.slice, offset)
+    }
+
+    // 扫描字符串余下的部分。不含转义时返回字符串内容在输入中的范围；含有
+    // 转义时把解码结果写入 scratch 并返回 None
+    //
+    // 字符串的长度在遇到结尾的引号或转义时检查，错误指向该位置
+    fn scan_str(
+        &mut self,
+        scratch: &mut Vec<u8>,
+        max_len: usize,
+    ) -> Result<Option<(usize, usize)>> {
+        let mut start = self.index;
+        let mut escaped = false;
+        loop {
+            let rest = &self.slice[self.index..self.limit];
+            let len = match find_special(rest) {
+                Some(len) => len,
+                None => {
+                    self.index = self.limit;
+                    return Err(self.end_error());
+                }
+            };
+            self.index += len;
+            check_str_len(self, scratch.len() + self.index - start, max_len)?;
+            match self.slice[self.index] {
+                b'"' if escaped => {
+                    scratch.extend_from_slice(&self.slice[start..self.index]);
+                    self.index += 1;
+                    return Ok(None);
+                }
+                b'"' => {
+                    let range = (start, self.index);
+                    self.index += 1;
+                    return Ok(Some(range));
+                }
+                b'\\' => {
+                    scratch.extend_from_slice(&self.slice[start..self.index]);
+                    self.index += 1;
+                    parse_escape(self, scratch)?;
+                    check_str_len(self, scratch.len(), max_len)?;
+                    start = self.index;
+                    escaped = true;
+                }
+                // RFC 8259 要求 U+0000 到 U+001F 的控制字符必须转义。控制字符
+                // 不消费，错误指向它所在的位置
+                b => {
+                    let err = self.error(ErrorCode::ControlCharacterInString);
+                    return Err(err.with_found(char::from(b)));
+                }
+            }
+        }
+    }
+}
+
+impl<'de> private::Sealed for SliceRead<'de> {}
+
+// 解析器对每个字节都要调用 `peek`、`next`、`discard`。它们不是泛型函数，
+// 没有 `#[inline]` 时无法内联到在调用方 crate 中实例化的解析器里
+impl<'de> Read<'de> for SliceRead<'de> {
+    #[inline]
+    fn peek(&mut self) -> Result<Option<u8>> {
+        if self.index < self.limit {
+            Ok(Some(self.slice[self.index]))
+        } else if self.index < self.slice.len() {
+            Err(self.error(ErrorCode::InputTooLong))
+        } else {
+            Ok(None)
+        }
+    }
+
+    #[inline]
+    fn next(&mut self) -> Result<Option<u8>> {
+        let b = self.peek()?;
+        if b.is_some() {
+            self.index += 1;
+        }
+        Ok(b)
+    }
+
+    #[inline]
+    fn discard(&mut self) {
+        self.index += 1;
+    }
+
+    #[inline]
+    fn skip_whitespace(&mut self) -> Result<Option<u8>> {
+        // 紧凑的输出中下一个字节通常就不是空白，不必按字查找
+        if self.index < self.limit && !is_whitespace(self.slice[self.index]) {
+            return Ok(Some(self.slice[self.index]));
+        }
+        self.index += whitespace_len(&self.slice[self.index..self.limit]);
+        self.peek()
+    }
+
+    fn position(&self) -> Position {
+        self.position_of(self.index)
+    }
+
+    #[inline]
+    fn byte_offset(&self) -> usize {
+        self.index
+    }
+
+    fn parse_str<'s>(
+        &'s mut self,
+        scratch: &'s mut Vec<u8>,
+        max_len: usize,
+    ) -> Result<Reference<'de, 's>> {
+        match self.scan_str(scratch, max_len)? {
+            Some((start, end)) => match str::from_utf8(&self.slice[start..end]) {
+                Ok(s) => Ok(Reference::Borrowed(s)),
+                Err(err) => {
+                    let position = self.position_of(start + err.valid_up_to());
+                    Err(Error::new(ErrorCode::InvalidUtf8, Some(position)))
+                }
+            },
+            None => copied(self, scratch),
+        }
+    }
+
+    fn set_max_input_len(&mut self, limit: usize) {
+        // 已经读过的部分不受影响
+        self.limit = self.slice.len().min(limit).max(self.index);
+    }
+}
+
+// 从字符串切片读取。输入已知是合法的 UTF-8，借用时无需再次检查
+pub struct StrRead<'de> {
+    input: &'de str,
+    delegate: SliceRead<'de>,
+}
+
+impl<'de> StrRead<'de> {
+    pub fn new(input: &'de str) -> Self {
+        StrRead {
+            input,
+            delegate: SliceRead::new(input.as_bytes()),
+        }
+    }
+}
+
+impl<'de> private::Sealed for StrRead<'de> {}
+
+impl<'de> Read<'de> for StrRead<'de> {
+    #[inline]
+    fn peek(&mut self) -> Result<Option<u8>> {
+        self.delegate.peek()
+    }
+
+    #[inline]
+    fn next(&mut self) -> Result<Option<u8>> {
+        self.delegate.next()
+    }
+
+    #[inline]
+    fn discard(&mut self) {
+        self.delegate.discard()
+    }
+
+    #[inline]
+    fn skip_whitespace(&mut self) -> Result<Option<u8>> {
+        self.delegate.skip_whitespace()
+    }
+
+    fn position(&self) -> Position {
+        self.delegate.position()
+    }
+
+    #[inline]
+    fn byte_offset(&self) -> usize {
+        self.delegate.byte_offset()
+    }
+
+    fn parse_str<'s>(
+        &'s mut self,
+        scratch: &'s mut Vec<u8>,
+        max_len: usize,
+    ) -> Result<Reference<'de, 's>> {
+        match self.delegate.scan_str(scratch, max_len)? {
+            // 范围的两端都是 ASCII 字符，切分一定落在字符边界上
+            Some((start, end)) => Ok(Reference::Borrowed(&self.input[start..end])),
+            None => copied(self, scratch),
+        }
+    }
+
+    fn set_max_input_len(&mut self, limit: usize) {
+        self.delegate.set_max_input_len(limit)
+    }
+}
+
+// 读取器内部缓冲区的大小
+const BUFFER_SIZE: usize = 8 * 1024;
+
+// 从 `io::Read` 读取，每次向内部缓冲区读入一块。读取器的内容无法借用，
+// 字符串总是解码到 scratch 中
+pub struct IoRead<R> {
+    reader: R,
+    buf: Box<[u8]>,
+    // 缓冲区中下一个未读字节的下标，以及有效数据的长度
+    pos: usize,
+    len: usize,
+    // 已消费字节的位置信息，随读取逐步更新
+    line: usize,
+    column: usize,
+    offset: usize,
+    // 最多从 reader 读取的字节数
+    limit: usize,
+}
+
+impl<R: io::Read> IoRead<R> {
+    pub fn new(reader: R) -> Self {
+        IoRead {
+            reader,
+            buf: vec![0; BUFFER_SIZE].into_boxed_slice(),
+            pos: 0,
+            len: 0,
+            line: 1,
+            column: 1,
+            offset: 0,
+            limit: usize::MAX,
+        }
+    }
+
+    // 缓冲区已经读完时从底层读取下一块，返回是否还有数据。缓冲区读完时
+    // 已读取的字节都已消费，`offset` 就是从 reader 读取的总字节数；达到
+    // 限制后再试读一个字节，以区分输入恰好结束和超出限制
+    fn fill(&mut self) -> Result<bool> {
+        if self.pos < self.len {
+            return Ok(true);
+        }
+        let len = BUFFER_SIZE.min(self.limit.saturating_sub(self.offset));
+        loop {
+            match self.reader.read(&mut self.buf[..len.max(1)]) {
+                Ok(n) if n > 0 && len == 0 => return Err(self.error(ErrorCode::InputTooLong)),
+                Ok(len) => {
+                    self.pos = 0;
+                    self.len = len;
+                    return Ok(len > 0);
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(self.error(ErrorCode::Io(err))),
+            }
+        }
+    }
+
+    // 消费缓冲区中的 `len` 个字节并更新位置。列号按字符计数，UTF-8 的
+    // 后续字节（0b10xx_xxxx）不计入
+    fn consume(&mut self, len: usize) {
+        for &b in &self.buf[self.pos..self.pos + len] {
+            if b == b'\n' {
+                self.line += 1;
+                self.column = 1;
+            } else if b & 0xC0 != 0x80 {
+                self.column += 1;
+            }
+        }
+        self.pos += len;
+        self.offset += len;
+    }
+}
+
+impl<R: io::Read> private::Sealed for IoRead<R> {}
+
+impl<'de, R: io::Read> Read<'de> for IoRead<R> {
+    fn peek(&mut self) -> Result<Option<u8>> {
+        if self.fill()? {
+            Ok(Some(self.buf[self.pos]))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn next(&mut self) -> Result<Option<u8>> {
+        let b = self.peek()?;
+        if b.is_some() {
+            self.consume(1);
+        }
+        Ok(b)
+    }
+
+    fn discard(&mut self) {
+        if self.pos < self.len {
+            self.consume(1);
+        }
+    }
+
+    fn position(&self) -> Position {
+        Position {
+            line: self.line,
+            column: self.column,
+            offset: self.offset,
+        }
+    }
+
+    fn byte_offset(&self) -> usize {
+        self.offset
+    }
+
+    // 长度随着读取逐块检查，超长的字符串不会被完整地读入内存
+    fn parse_str<'s>(
+        &'s mut self,
+        scratch: &'s mut Vec<u8>,
+        max_len: usize,
+    ) -> Result<Reference<'de, 's>> {
+        loop {
+            if !self.fill()? {
+                return Err(self.error(ErrorCode::Eof));
+            }
+            let chunk = &self.buf[self.pos..self.len];
+            let len = find_special(chunk).unwrap_or(chunk.len());
+            check_str_len(self, scratch.len() + len, max_len)?;
+            scratch.extend_from_slice(&chunk[..len]);
+            self.consume(len);
+            if self.pos == self.len {
+                // 这一块读完了还没有遇到特殊字节，继续读下一块
+                continue;
+            }
+            match self.buf[self.pos] {
+                b'"' => {
+                    self.consume(1);
+                    return copied(self, scratch);
+                }
+                b'\\' => {
+                    self.consume(1);
+                    parse_escape(self, scratch)?;
+                    check_str_len(self, scratch.len(), max_len)?;
+                }
+                b => {
+                    let err = self.error(ErrorCode::ControlCharacterInString);
+                    return Err(err.with_found(char::from(b)));
+                }
+            }
+        }
+    }
+
+    fn set_max_input_len(&mut self, limit: usize) {
+        self.limit = limit;
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 
 #[test]
@@ -665,3 +2028,1213 @@ fn test_enum() {
     let expected = E::Struct { a: 1 };
     assert_eq!(expected, from_str(j).unwrap());
 }
+
+#[test]
+fn test_whitespace() {
+    use std::collections::BTreeMap;
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    enum E {
+        Unit,
+        Tuple(u32, u32),
+        Struct { a: u32 },
+    }
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Test {
+        int: u32,
+        seq: Vec<String>,
+        opt: Option<bool>,
+        unit: (),
+        e: Vec<E>,
+    }
+
+    let j = " \t{\r\n  \"int\" : 1 ,\n  \"seq\" : [ \"a\" , \"b\" ],\n  \"opt\" : null,\n  \"unit\" : null,\n  \"e\" : [ \"Unit\" , { \"Tuple\" : [ 1 , 2 ] } , { \"Struct\" : { \"a\" : 1 } } ]\n}\n";
+    let expected = Test {
+        int: 1,
+        seq: vec!["a".to_owned(), "b".to_owned()],
+        opt: None,
+        unit: (),
+        e: vec![E::Unit, E::Tuple(1, 2), E::Struct { a: 1 }],
+    };
+    assert_eq!(expected, from_str(j).unwrap());
+
+    let empty: Vec<u32> = from_str("[ ]").unwrap();
+    assert!(empty.is_empty());
+
+    // 字符串内部的空白字符原样保留
+    assert_eq!(" a b ", from_str::<String>("  \" a b \"  ").unwrap());
+    assert!(matches!(from_str::<u32>(" 1 x").unwrap_err().code(), ErrorCode::TrailingCharacters));
+
+    // 较长的空白按字跳过，结果和逐字节读取的 `from_reader` 一致
+    let mut map = BTreeMap::new();
+    map.insert("a".to_owned(), 2);
+    let expected: (u32, BTreeMap<String, u32>) = (1, map);
+    for len in 0..40 {
+        let ws: String = " \t\n\r".chars().cycle().take(len).collect();
+        let j = format!("{0}[{0}1{0},{0}{{{0}\"a\"{0}:{0}2{0}}}{0}]{0}", ws);
+        assert_eq!(expected, from_str(&j).unwrap());
+        assert_eq!(expected, from_reader(j.as_bytes()).unwrap());
+
+        let j = format!("[1{}x]", ws);
+        let err = from_slice::<Vec<u32>>(j.as_bytes()).unwrap_err();
+        assert!(matches!(err.code(), ErrorCode::ExpectedArrayComma));
+        assert_eq!(len + 2, err.offset());
+        let err = from_reader::<_, Vec<u32>>(j.as_bytes()).unwrap_err();
+        assert_eq!(len + 2, err.offset());
+    }
+}
+
+#[test]
+fn test_integer() {
+    assert_eq!(Some(255u8), from_str("255").ok());
+    assert_eq!(Some(0u8), from_str("-0").ok());
+    assert_eq!(Some(u64::MAX), from_str("18446744073709551615").ok());
+    assert_eq!(Some(-128i8), from_str("-128").ok());
+    assert_eq!(Some(127i8), from_str("127").ok());
+    assert_eq!(Some(i16::MIN), from_str("-32768").ok());
+    assert_eq!(Some(i32::MAX), from_str("2147483647").ok());
+    assert_eq!(Some(i64::MIN), from_str("-9223372036854775808").ok());
+    assert_eq!(Some(i64::MAX), from_str("9223372036854775807").ok());
+    assert_eq!(Some(vec![-1i64, 2, -3]), from_str("[-1, 2, -3]").ok());
+
+    assert!(matches!(from_str::<u8>("256").unwrap_err().code(), ErrorCode::NumberOutOfRange("u8")));
+    assert!(matches!(from_str::<u8>("300").unwrap_err().code(), ErrorCode::NumberOutOfRange("u8")));
+    assert!(matches!(from_str::<u16>("-1").unwrap_err().code(), ErrorCode::NumberOutOfRange("u16")));
+    assert!(matches!(from_str::<u64>("18446744073709551616").unwrap_err().code(), ErrorCode::NumberOutOfRange("u64")));
+    assert!(matches!(from_str::<i8>("128").unwrap_err().code(), ErrorCode::NumberOutOfRange("i8")));
+    assert!(matches!(from_str::<i8>("-129").unwrap_err().code(), ErrorCode::NumberOutOfRange("i8")));
+    assert!(matches!(from_str::<i32>("2147483648").unwrap_err().code(), ErrorCode::NumberOutOfRange("i32")));
+    assert!(matches!(from_str::<i64>("-9223372036854775809").unwrap_err().code(), ErrorCode::NumberOutOfRange("i64")));
+    assert!(matches!(from_str::<i64>("99999999999999999999999").unwrap_err().code(), ErrorCode::NumberOutOfRange("i64")));
+    assert!(matches!(from_str::<i32>("-").unwrap_err().code(), ErrorCode::InvalidNumber));
+    assert!(matches!(from_str::<i32>("- 1").unwrap_err().code(), ErrorCode::InvalidNumber));
+    assert!(matches!(from_str::<i32>("1.5").unwrap_err().code(), ErrorCode::ExpectedInteger));
+    assert!(matches!(from_str::<u32>("1e3").unwrap_err().code(), ErrorCode::ExpectedInteger));
+    assert!(matches!(from_str::<u32>("true").unwrap_err().code(), ErrorCode::ExpectedInteger));
+}
+
+#[test]
+fn test_float() {
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Point2D(f64, f64);
+
+    assert_eq!(Some(Point2D(1.5, -2.0)), from_str("[1.5, -2]").ok());
+    assert_eq!(Some(0.1f64), from_str("0.1").ok());
+    assert_eq!(Some(-1.5e-3f64), from_str("-1.5E-3").ok());
+    assert_eq!(Some(1e10f64), from_str("1e+10").ok());
+    assert_eq!(Some(f64::MAX), from_str("1.7976931348623157e308").ok());
+    assert_eq!(Some(2.2250738585072014e-308f64), from_str("2.2250738585072014e-308").ok());
+    assert_eq!(Some(0.0f64), from_str("1e-400").ok());
+    // 直接按 f32 精度舍入：先解析为 f64 会舍入到 1 + 2^-24，再舍入到 f32 时
+    // 恰好位于中点而得到 1.0
+    assert_eq!(Some(1.000_000_1f32), from_str("1.00000005960464477539062500001").ok());
+    assert_eq!(Some(16777216f32), from_str("16777217").ok());
+
+    assert!(matches!(from_str::<f64>("1e400").unwrap_err().code(), ErrorCode::NumberOutOfRange("f64")));
+    assert!(matches!(from_str::<f32>("1e39").unwrap_err().code(), ErrorCode::NumberOutOfRange("f32")));
+    assert!(matches!(from_str::<f64>("1.").unwrap_err().code(), ErrorCode::InvalidNumber));
+    assert!(matches!(from_str::<f64>("1e").unwrap_err().code(), ErrorCode::InvalidNumber));
+    assert!(matches!(from_str::<f64>("-.5").unwrap_err().code(), ErrorCode::InvalidNumber));
+    assert!(matches!(from_str::<f64>(".5").unwrap_err().code(), ErrorCode::InvalidNumber));
+    assert!(matches!(from_str::<f64>("\"1.0\"").unwrap_err().code(), ErrorCode::ExpectedFloat));
+}
+
+#[test]
+fn test_any_number() {
+    use std::fmt;
+
+    // 记录 deserialize_any 调用了哪个 visitor 方法
+    #[derive(PartialEq, Debug)]
+    enum Number {
+        U64(u64),
+        I64(i64),
+        F64(f64),
+    }
+
+    impl<'de> Deserialize<'de> for Number {
+        fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+        where
+            D: de::Deserializer<'de>,
+        {
+            struct NumberVisitor;
+
+            impl<'de> Visitor<'de> for NumberVisitor {
+                type Value = Number;
+
+                fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                    formatter.write_str("a number")
+                }
+
+                fn visit_u64<E>(self, v: u64) -> std::result::Result<Number, E> {
+                    Ok(Number::U64(v))
+                }
+
+                fn visit_i64<E>(self, v: i64) -> std::result::Result<Number, E> {
+                    Ok(Number::I64(v))
+                }
+
+                fn visit_f64<E>(self, v: f64) -> std::result::Result<Number, E> {
+                    Ok(Number::F64(v))
+                }
+            }
+
+            deserializer.deserialize_any(NumberVisitor)
+        }
+    }
+
+    let j = "[1, -1, 1.0, 1e2, -0.5E-1]";
+    let expected = vec![
+        Number::U64(1),
+        Number::I64(-1),
+        Number::F64(1.0),
+        Number::F64(100.0),
+        Number::F64(-0.05),
+    ];
+    assert_eq!(expected, from_str::<Vec<Number>>(j).unwrap());
+}
+
+#[test]
+fn test_string_escape() {
+    assert_eq!(Some("a\"b".to_owned()), from_str(r#""a\"b""#).ok());
+    assert_eq!(Some("\\ / \u{8} \u{c} \n \r \t".to_owned()), from_str(r#""\\ \/ \b \f \n \r \t""#).ok());
+    assert_eq!(Some("é\u{FFFF}".to_owned()), from_str(r#""\u00e9\uFFFF""#).ok());
+    assert_eq!(Some("😀".to_owned()), from_str(r#""\ud83d\uDE00""#).ok());
+    assert_eq!(Some(vec!["x\ny".to_owned(), "中文".to_owned()]), from_str(r#"["x\ny", "\u4e2d\u6587"]"#).ok());
+
+    // 解码到 `String` 之后 scratch 的分配留在反序列化器中，供下一个字符串复用
+    let mut de = Deserializer::from_str(r#""x\ny""#);
+    assert_eq!("x\ny", String::deserialize(&mut de).unwrap());
+    assert!(de.scratch.capacity() > 0);
+
+    // 不含转义的字符串可以借用，含有转义的则不行
+    assert_eq!(Some("plain"), from_str::<&str>(r#""plain""#).ok());
+    let err = from_str::<&str>(r#""a\nb""#).unwrap_err();
+    assert!(err.to_string().contains("expected a borrowed string"));
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    enum E {
+        #[serde(rename = "a\"b")]
+        Quoted,
+    }
+    assert_eq!(Some(E::Quoted), from_str(r#""a\"b""#).ok());
+
+    assert!(matches!(from_str::<String>(r#""\ud800""#).unwrap_err().code(), ErrorCode::LoneSurrogate));
+    assert!(matches!(from_str::<String>(r#""\ud800x""#).unwrap_err().code(), ErrorCode::LoneSurrogate));
+    assert!(matches!(from_str::<String>(r#""\ud800\u0041""#).unwrap_err().code(), ErrorCode::LoneSurrogate));
+    assert!(matches!(from_str::<String>(r#""\udc00""#).unwrap_err().code(), ErrorCode::LoneSurrogate));
+    assert!(matches!(from_str::<String>(r#""\u12g4""#).unwrap_err().code(), ErrorCode::InvalidUnicodeEscape));
+    assert!(matches!(from_str::<String>(r#""\x41""#).unwrap_err().code(), ErrorCode::InvalidEscape));
+    assert!(matches!(from_str::<String>("\"a\nb\"").unwrap_err().code(), ErrorCode::ControlCharacterInString));
+    assert!(matches!(from_str::<String>(r#""abc"#).unwrap_err().code(), ErrorCode::Eof));
+    assert!(matches!(from_str::<String>(r#""\u12"#).unwrap_err().code(), ErrorCode::Eof));
+}
+
+#[test]
+fn test_error_position() {
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct Test {
+        a: u32,
+        b: u32,
+    }
+
+    let err = from_str::<Test>("{\n  \"a\": 1,\n  \"b\" 2\n}").unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::ExpectedMapColon));
+    assert_eq!((3, 7, 18), (err.line(), err.column(), err.offset()));
+
+    // 列号按字符计数，偏移按字节计数
+    let err = from_str::<Vec<String>>("[\"é\", x]").unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::ExpectedString));
+    assert_eq!((1, 7, 7), (err.line(), err.column(), err.offset()));
+
+    // 通过 `de::Error::custom` 创建的错误同样带有位置
+    let err = from_str::<Test>("{\n  \"a\": 1\n}").unwrap_err();
+    assert_eq!("missing field `b` at line 3 column 1", err.to_string());
+
+    let err = from_str::<u32>("1\n\n  2").unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::TrailingCharacters));
+    assert_eq!(Some(Position { line: 3, column: 3, offset: 5 }), err.position());
+}
+
+#[test]
+fn test_error_classify() {
+    use super::ch05_00_error::Category;
+
+    let err = from_str::<Vec<u32>>("[1 2]").unwrap_err();
+    assert_eq!(Category::Syntax, err.classify());
+    assert_eq!(
+        "expected `,` or `]` after array element, found '2' at line 1 column 4",
+        err.to_string()
+    );
+
+    let err = from_str::<u32>("\"1\"").unwrap_err();
+    assert_eq!(Category::Data, err.classify());
+    assert_eq!("expected an integer, found '\"' at line 1 column 1", err.to_string());
+
+    let err = from_str::<u32>("x").unwrap_err();
+    assert_eq!(Category::Syntax, err.classify());
+
+    let err = from_str::<String>("\"a\tb\"").unwrap_err();
+    assert_eq!(Category::Syntax, err.classify());
+    assert_eq!(
+        "control character (U+0000 to U+001F) must be escaped in string, found '\\t' at line 1 column 3",
+        err.to_string()
+    );
+
+    let err = from_str::<Vec<u32>>("[1,").unwrap_err();
+    assert_eq!(Category::Eof, err.classify());
+    assert_eq!("unexpected end of input at line 1 column 4", err.to_string());
+
+    let err = from_str::<u8>("256").unwrap_err();
+    assert_eq!(Category::Data, err.classify());
+    assert_eq!("number out of range for u8 at line 1 column 4", err.to_string());
+
+    let err = from_str::<(u32,)>("[1, 2]").unwrap_err();
+    assert_eq!(Category::Syntax, err.classify());
+    assert_eq!("expected `]` at end of array, found ',' at line 1 column 3", err.to_string());
+}
+
+#[test]
+fn test_from_slice() {
+    use std::borrow::Cow;
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Test<'a> {
+        name: &'a str,
+        #[serde(borrow)]
+        note: Cow<'a, str>,
+    }
+
+    let j = "{\"name\":\"café\",\"note\":\"a\\nb\"}";
+    let value: Test = from_slice(j.as_bytes()).unwrap();
+    assert_eq!("café", value.name);
+    assert!(matches!(value.note, Cow::Owned(ref s) if s == "a\nb"));
+
+    // 字符串中的非法 UTF-8 指向出错的字节
+    let err = from_slice::<Vec<String>>(b"[\"ab\xff\"]").unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::InvalidUtf8));
+    assert_eq!((1, 5, 4), (err.line(), err.column(), err.offset()));
+
+    let err = from_slice::<Vec<String>>(b"[\"a\\n\xc3\"]").unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::InvalidUtf8));
+
+    let err = from_slice::<u32>(b"\xff").unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::ExpectedInteger));
+}
+
+#[test]
+fn test_from_reader() {
+    use std::borrow::Cow;
+    use std::io;
+
+    use super::ch05_00_error::Category;
+
+    // 每次只读出一个字节，覆盖缓冲区在任意位置耗尽的情况
+    struct OneByte<'a>(&'a [u8]);
+
+    impl<'a> io::Read for OneByte<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.split_first() {
+                Some((&b, rest)) if !buf.is_empty() => {
+                    buf[0] = b;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Test<'a> {
+        int: i32,
+        #[serde(borrow)]
+        name: Cow<'a, str>,
+        seq: Vec<String>,
+    }
+
+    let j = "{\"int\": -1, \"name\": \"café\", \"seq\": [\"a\\u00e9\", \"\\ud83d\\ude00\"]}";
+    let expected = Test {
+        int: -1,
+        name: Cow::Borrowed("café"),
+        seq: vec!["aé".to_owned(), "😀".to_owned()],
+    };
+    // `Test` 借用输入，不是 `DeserializeOwned`，只能直接使用 Deserializer
+    let mut de = Deserializer::from_reader(OneByte(j.as_bytes()));
+    let value = Test::deserialize(&mut de).unwrap();
+    de.end().unwrap();
+    assert_eq!(expected, value);
+    // 读取器中的字符串无法借用，退回到拥有所有权的字符串
+    assert!(matches!(value.name, Cow::Owned(_)));
+
+    // 跨越多个缓冲区的长字符串
+    let long = "x".repeat(3 * 8 * 1024 + 7);
+    let j = format!("[\"{}\", 1]", long);
+    let value: (String, u8) = from_reader(j.as_bytes()).unwrap();
+    assert_eq!((long, 1), value);
+
+    let err = from_reader::<_, Vec<u32>>(OneByte(b"[1,\n 2 x]")).unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::ExpectedArrayComma));
+    assert_eq!((2, 4, 7), (err.line(), err.column(), err.offset()));
+
+    let err = from_reader::<_, u32>(OneByte(b"1 2")).unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::TrailingCharacters));
+
+    struct FailingReader;
+
+    impl io::Read for FailingReader {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::ConnectionReset, "reset"))
+        }
+    }
+
+    let err = from_reader::<_, u32>(FailingReader).unwrap_err();
+    assert_eq!(Category::Io, err.classify());
+}
+
+#[test]
+fn test_stream() {
+    use serde::de::IgnoredAny;
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Record {
+        id: u32,
+    }
+
+    let j = "{\"id\":1}\n{\"id\":2}\n\n  {\"id\":3}\n";
+    let mut stream = StreamDeserializer::<_, Record>::new(StrRead::new(j));
+    assert_eq!(Record { id: 1 }, stream.next().unwrap().unwrap());
+    assert_eq!(8, stream.byte_offset());
+    assert_eq!(Record { id: 2 }, stream.next().unwrap().unwrap());
+    assert_eq!(Record { id: 3 }, stream.next().unwrap().unwrap());
+    assert_eq!(29, stream.byte_offset());
+    assert!(stream.next().is_none());
+
+    // 对象、数组和字符串可以首尾相连，数字之间必须有空白
+    let values: Vec<Vec<u32>> = StreamDeserializer::new(StrRead::new("[1][2, 3] []"))
+        .collect::<Result<_>>()
+        .unwrap();
+    assert_eq!(vec![vec![1], vec![2, 3], vec![]], values);
+
+    let mut stream = StreamDeserializer::<_, u32>::new(SliceRead::new(b"1 2 3x 4"));
+    assert_eq!(Some(1), stream.next().and_then(Result::ok));
+    assert_eq!(Some(2), stream.next().and_then(Result::ok));
+    let err = stream.next().unwrap().unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::TrailingCharacters));
+    assert_eq!(5, err.offset());
+    assert_eq!(3, stream.byte_offset());
+    // 出错之后迭代结束
+    assert!(stream.next().is_none());
+
+    let lines = "{\"id\":1}\n{\"id\":2}\n";
+    let stream = StreamDeserializer::<_, Record>::new(IoRead::new(lines.as_bytes()));
+    assert_eq!(2, stream.map(Result::unwrap).count());
+
+    // 反序列化器的设置对流中的每个值生效
+    let mut de = Deserializer::from_str("[1, 2] [1, 2, 3]");
+    de.set_limits(Limits {
+        max_elements: Some(2),
+        ..Limits::default()
+    });
+    let mut stream = StreamDeserializer::<_, IgnoredAny>::from_deserializer(de);
+    assert!(stream.next().unwrap().is_ok());
+    let err = stream.next().unwrap().unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::TooManyElements));
+
+    let mut de = Deserializer::from_str("[[[1]]]");
+    de.set_recursion_limit(2);
+    let mut stream = StreamDeserializer::<_, IgnoredAny>::from_deserializer(de);
+    let err = stream.next().unwrap().unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::RecursionLimitExceeded));
+
+    let mut de = Deserializer::from_str("1 007");
+    de.set_number_mode(NumberMode::Strict);
+    let mut stream = StreamDeserializer::<_, u32>::from_deserializer(de);
+    assert_eq!(1, stream.next().unwrap().unwrap());
+    let err = stream.next().unwrap().unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::InvalidNumber));
+}
+
+#[test]
+fn test_any() {
+    use std::collections::BTreeMap;
+
+    use super::ch05_03_value::Value;
+
+    // `deserialize_any` 根据输入选择 visitor 方法
+    let j = r#"[0, -1, 18446744073709551615, -9223372036854775808, 1.5, -2e3, "a\"b", null, true, [], {}]"#;
+    let value: Value = from_str(j).unwrap();
+    assert_eq!(Some(0), value[0].as_u64());
+    assert_eq!(Some(-1), value[1].as_i64());
+    assert_eq!(Some(u64::MAX), value[2].as_u64());
+    assert_eq!(Some(i64::MIN), value[3].as_i64());
+    assert_eq!(Some(1.5), value[4].as_f64());
+    assert_eq!(Some(-2000.0), value[5].as_f64());
+    assert_eq!(value[6], "a\"b");
+    assert!(value[7].is_null());
+    assert_eq!(value[8], true);
+    assert_eq!(Some(0), value[9].as_array().map(Vec::len));
+    assert_eq!(Some(0), value[10].as_object().map(BTreeMap::len));
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Inner {
+        b: i32,
+    }
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Outer {
+        a: f64,
+        #[serde(flatten)]
+        inner: Inner,
+        #[serde(flatten)]
+        rest: BTreeMap<String, Value>,
+    }
+
+    let j = r#"{"a": -1.5, "b": -7, "c": "x\ny", "d": [1, {"e": null}]}"#;
+    let outer: Outer = from_str(j).unwrap();
+    assert_eq!(-1.5, outer.a);
+    assert_eq!(Inner { b: -7 }, outer.inner);
+    assert_eq!(outer.rest["c"], "x\ny");
+    assert!(outer.rest["d"][1]["e"].is_null());
+}
+
+#[test]
+fn test_enum_representations() {
+    use serde::Serialize;
+
+    use super::ch01_overview::State;
+    use super::ch05_01_serializer::to_string;
+
+    // 与 `ch01_overview::State` 相同的变体，分别使用 serde 的四种枚举表示。
+    // `Ready` 被跳过、`Waiting` 使用了自定义的序列化函数，它们无法往返，
+    // 单独在下面检查
+    macro_rules! state {
+        ($name:ident $(, $attr:meta)*) => {
+            #[derive(Serialize, Deserialize, PartialEq, Debug)]
+            $(#[serde($attr)])*
+            enum $name {
+                Running(i32),
+                Stop,
+                Block,
+                Waiting { a: i32, b: bool },
+                Unknown,
+            }
+        };
+    }
+
+    state!(External);
+    state!(Internal, tag = "type");
+    state!(Adjacent, tag = "t", content = "c");
+    state!(Untagged, untagged);
+
+    fn round_trip<T>(value: T, expected: &str)
+    where
+        T: Serialize + for<'de> Deserialize<'de> + PartialEq + std::fmt::Debug,
+    {
+        let j = to_string(&value).unwrap();
+        assert_eq!(expected, j);
+        assert_eq!(value, from_str::<T>(&j).unwrap());
+    }
+
+    round_trip(External::Running(-1), r#"{"Running":-1}"#);
+    round_trip(External::Stop, r#""Stop""#);
+    round_trip(External::Waiting { a: 1, b: true }, r#"{"Waiting":{"a":1,"b":true}}"#);
+
+    round_trip(Internal::Stop, r#"{"type":"Stop"}"#);
+    round_trip(Internal::Waiting { a: -1, b: false }, r#"{"type":"Waiting","a":-1,"b":false}"#);
+    // 内部标记无法表示包含整数的 newtype variant，这是 serde 的限制
+    assert!(to_string(&Internal::Running(1)).is_err());
+    // 标记不必是第一个成员
+    assert_eq!(Internal::Block, from_str(r#"{ "type" : "Block" }"#).unwrap());
+    assert_eq!(
+        Internal::Waiting { a: 2, b: true },
+        from_str(r#"{"b": true, "a": 2, "type": "Waiting"}"#).unwrap()
+    );
+
+    round_trip(Adjacent::Running(-1), r#"{"t":"Running","c":-1}"#);
+    round_trip(Adjacent::Unknown, r#"{"t":"Unknown"}"#);
+    round_trip(Adjacent::Waiting { a: 1, b: true }, r#"{"t":"Waiting","c":{"a":1,"b":true}}"#);
+    assert_eq!(Adjacent::Running(3), from_str(r#"{"c": 3, "t": "Running"}"#).unwrap());
+
+    round_trip(Untagged::Running(-1), "-1");
+    round_trip(Untagged::Stop, "null");
+    round_trip(Untagged::Waiting { a: 1, b: true }, r#"{"a":1,"b":true}"#);
+    // 不带标记时所有的 unit variant 都是 null，读回来总是第一个 unit variant
+    assert_eq!("null", to_string(&Untagged::Block).unwrap());
+    assert_eq!(Untagged::Stop, from_str("null").unwrap());
+
+    // ch01_overview::State 本身：外部标记
+    assert_eq!(r#"{"Running":-1}"#, to_string(&State::Running(-1)).unwrap());
+    assert!(matches!(from_str(r#"{"Running":-1}"#).unwrap(), State::Running(-1)));
+    assert!(matches!(from_str(r#""Unknown""#).unwrap(), State::Unknown));
+    // `#[serde(skip)]` 的变体无法序列化，也不会被反序列化
+    assert!(to_string(&State::Ready).is_err());
+    assert!(from_str::<State>(r#""Ready""#).is_err());
+    // `serialize_with` 只影响序列化，反序列化仍然使用结构体形式
+    assert_eq!(r#"{"Waiting":"Waiting"}"#, to_string(&State::Waiting { a: 1, b: false }).unwrap());
+    assert!(matches!(
+        from_str(r#"{"Waiting":{"a":1,"b":false}}"#).unwrap(),
+        State::Waiting { a: 1, b: false }
+    ));
+}
+
+#[test]
+fn test_bytes() {
+    use serde_bytes::ByteBuf;
+
+    fn from_str_with(bytes: BytesMode, input: &str) -> Result<ByteBuf> {
+        let mut deserializer = Deserializer::from_str(input);
+        deserializer.set_bytes_mode(bytes);
+        let value = ByteBuf::deserialize(&mut deserializer)?;
+        deserializer.end()?;
+        Ok(value)
+    }
+
+    assert_eq!(b"hi", &from_str::<ByteBuf>("[104, 105]").unwrap()[..]);
+    assert_eq!(b"", &from_str::<ByteBuf>(" [ ] ").unwrap()[..]);
+    // 数字数组在任何设置下都能读取
+    assert_eq!(b"hi", &from_str_with(BytesMode::Base64, "[104,105]").unwrap()[..]);
+    // `BytesMode::Array` 下字符串原样交给 visitor
+    assert_eq!(b"hi", &from_str::<ByteBuf>("\"hi\"").unwrap()[..]);
+
+    // RFC 4648 第 10 节的测试向量
+    let cases = [
+        ("", "\"\""),
+        ("f", "\"Zg==\""),
+        ("fo", "\"Zm8=\""),
+        ("foo", "\"Zm9v\""),
+        ("foob", "\"Zm9vYg==\""),
+        ("fooba", "\"Zm9vYmE=\""),
+        ("foobar", "\"Zm9vYmFy\""),
+    ];
+    for &(expected, input) in &cases {
+        let bytes = from_str_with(BytesMode::Base64, input).unwrap();
+        assert_eq!(expected.as_bytes(), &bytes[..]);
+    }
+    assert_eq!(b"\xfb\xff", &from_str_with(BytesMode::Base64, "\"+/8=\"").unwrap()[..]);
+    assert_eq!(b"\x00\xff\x10", &from_str_with(BytesMode::Hex, "\"00Ff10\"").unwrap()[..]);
+
+    assert!(matches!(
+        from_str::<ByteBuf>("[1, 256]").unwrap_err().code(),
+        ErrorCode::NumberOutOfRange(_)
+    ));
+    assert!(matches!(from_str::<ByteBuf>("[-1]").unwrap_err().code(), ErrorCode::NumberOutOfRange(_)));
+    assert!(matches!(from_str::<ByteBuf>("1").unwrap_err().code(), ErrorCode::ExpectedArray));
+    for input in &["\"Zg\"", "\"Z===\"", "\"Zg==Zg==\"", "\"Zm9v!A==\""] {
+        let err = from_str_with(BytesMode::Base64, input).unwrap_err();
+        assert!(matches!(err.code(), ErrorCode::InvalidBase64), "{}", input);
+    }
+    let err = from_str_with(BytesMode::Hex, " \"0g\"").unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::InvalidHex));
+    assert_eq!((1, 2), (err.line(), err.column()));
+    let err = from_str_with(BytesMode::Hex, "[]0").unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::TrailingCharacters));
+    let err = from_str_with(BytesMode::Hex, "null").unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::ExpectedString));
+}
+
+#[test]
+fn test_map_key() {
+    use std::collections::{BTreeMap, HashMap};
+
+    #[derive(Deserialize, PartialEq, Eq, PartialOrd, Ord, Debug)]
+    enum Kind {
+        A,
+        B,
+    }
+
+    let map: BTreeMap<i32, u8> = from_str(r#"{"-1": 0, "42": 1}"#).unwrap();
+    assert_eq!(vec![(-1, 0), (42, 1)], map.into_iter().collect::<Vec<_>>());
+    let map: HashMap<bool, u8> = from_str(r#"{"true": 1}"#).unwrap();
+    assert_eq!(Some(&1), map.get(&true));
+    let map: BTreeMap<char, u8> = from_str(r#"{"x": 1}"#).unwrap();
+    assert_eq!(Some(&1), map.get(&'x'));
+    let map: BTreeMap<Kind, u8> = from_str(r#"{"B": 1, "A": 0}"#).unwrap();
+    assert_eq!(vec![(Kind::A, 0), (Kind::B, 1)], map.into_iter().collect::<Vec<_>>());
+
+    // 与序列化器往返
+    let mut map = HashMap::new();
+    map.insert(u64::MAX, vec![1]);
+    let json = super::ch05_01_serializer::to_string(&map).unwrap();
+    assert_eq!(map, from_str::<HashMap<u64, Vec<u8>>>(&json).unwrap());
+
+    for input in &[r#"{"": 0}"#, r#"{" 1": 0}"#, r#"{"1 ": 0}"#, r#"{"1x": 0}"#, r#"{"x": 0}"#] {
+        let err = from_str::<HashMap<u32, u8>>(input).unwrap_err();
+        assert!(matches!(err.code(), ErrorCode::InvalidMapKey), "{}", input);
+    }
+    let err = from_str::<HashMap<u8, u8>>(r#"{"256": 0}"#).unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::NumberOutOfRange(_)));
+    let err = from_str::<HashMap<u8, u8>>(r#"{1: 0}"#).unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::ExpectedString));
+}
+
+#[test]
+fn test_recursion_limit() {
+    use serde::de::IgnoredAny;
+
+    use super::ch05_03_value::Value;
+
+    fn nested(depth: usize) -> String {
+        "[".repeat(depth) + &"]".repeat(depth)
+    }
+
+    from_str::<IgnoredAny>(&nested(128)).unwrap();
+    let err = from_str::<IgnoredAny>(&nested(129)).unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::RecursionLimitExceeded));
+    assert_eq!((1, 130), (err.line(), err.column()));
+
+    // 足够深的嵌套不会耗尽调用栈
+    let err = from_str::<Value>(&"[".repeat(100_000)).unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::RecursionLimitExceeded));
+    let err = from_str::<Value>(&"{\"a\":".repeat(100_000)).unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::RecursionLimitExceeded));
+    let err = from_reader::<_, Value>("[".repeat(100_000).as_bytes()).unwrap_err();
+    assert_eq!(129, err.offset());
+
+    // 枚举也计入深度
+    #[derive(Deserialize, Debug)]
+    enum Tree {
+        Leaf,
+        Node(Box<Tree>),
+    }
+    fn tree(depth: usize) -> String {
+        "{\"Node\":".repeat(depth) + "\"Leaf\"" + &"}".repeat(depth)
+    }
+    let mut tree_depth = 0;
+    let mut node = from_str::<Tree>(&tree(128)).unwrap();
+    while let Tree::Node(child) = node {
+        tree_depth += 1;
+        node = *child;
+    }
+    assert_eq!(128, tree_depth);
+    let err = from_str::<Tree>(&tree(129)).unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::RecursionLimitExceeded));
+
+    let mut de = Deserializer::from_str("[[[]]]");
+    de.set_recursion_limit(2);
+    assert!(matches!(
+        IgnoredAny::deserialize(&mut de).unwrap_err().code(),
+        ErrorCode::RecursionLimitExceeded
+    ));
+
+    // 可信的输入可以取消限制
+    let input = nested(200);
+    let mut de = Deserializer::from_str(&input);
+    de.disable_recursion_limit();
+    IgnoredAny::deserialize(&mut de).unwrap();
+    de.end().unwrap();
+}
+
+#[test]
+fn test_limits() {
+    use super::ch05_00_error::Category;
+    use serde::de::IgnoredAny;
+
+    fn from_str_with<'a, T: Deserialize<'a>>(limits: Limits, input: &'a str) -> Result<T> {
+        let mut de = Deserializer::from_str(input);
+        de.set_limits(limits);
+        let value = T::deserialize(&mut de)?;
+        de.end()?;
+        Ok(value)
+    }
+
+    fn from_reader_with<T: DeserializeOwned>(limits: Limits, input: &str) -> Result<T> {
+        let mut de = Deserializer::from_reader(input.as_bytes());
+        de.set_limits(limits);
+        let value = T::deserialize(&mut de)?;
+        de.end()?;
+        Ok(value)
+    }
+
+    let strings = Limits {
+        max_string_len: Some(3),
+        ..Limits::default()
+    };
+    assert_eq!("abc", from_str_with::<String>(strings, "\"abc\"").unwrap());
+    assert_eq!("a\nb", from_reader_with::<String>(strings, "\"a\\nb\"").unwrap());
+    for input in &["\"abcd\"", "\"ab\\ncd\"", "{\"long\": 1}", "\"\\u00e9\\u00e9\""] {
+        let err = from_str_with::<IgnoredAny>(strings, input).unwrap_err();
+        assert!(matches!(err.code(), ErrorCode::StringTooLong), "{}", input);
+        let err = from_reader_with::<IgnoredAny>(strings, input).unwrap_err();
+        assert!(matches!(err.code(), ErrorCode::StringTooLong), "{}", input);
+    }
+
+    // 数字的文本（包括符号、小数点和指数）受 `max_number_len` 限制，与
+    // `max_string_len` 互不影响
+    assert_eq!(1234, from_str_with::<u64>(strings, "1234").unwrap());
+    let numbers = Limits {
+        max_number_len: Some(3),
+        ..Limits::default()
+    };
+    assert_eq!(-15, from_str_with::<i32>(numbers, "-15").unwrap());
+    assert_eq!("abcd", from_str_with::<String>(numbers, "\"abcd\"").unwrap());
+    for input in &["1234", "-1.50", "1e100", "[0.000000001]"] {
+        let err = from_str_with::<IgnoredAny>(numbers, input).unwrap_err();
+        assert!(matches!(err.code(), ErrorCode::NumberTooLong), "{}", input);
+        let err = from_reader_with::<IgnoredAny>(numbers, input).unwrap_err();
+        assert!(matches!(err.code(), ErrorCode::NumberTooLong), "{}", input);
+    }
+    let err = from_str_with::<u64>(numbers, "1234").unwrap_err();
+    assert_eq!(3, err.offset());
+
+    let elements = Limits {
+        max_elements: Some(2),
+        ..Limits::default()
+    };
+    assert_eq!(vec![vec![1, 2]], from_str_with::<Vec<Vec<u8>>>(elements, "[[1, 2]]").unwrap());
+    let err = from_str_with::<Vec<u8>>(elements, "[1, 2, 3]").unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::TooManyElements));
+    assert_eq!(7, err.offset());
+    let err = from_str_with::<IgnoredAny>(elements, r#"{"a":1,"b":2,"c":3}"#).unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::TooManyElements));
+
+    let input = Limits {
+        max_input_len: Some(8),
+        ..Limits::default()
+    };
+    assert_eq!(vec![1, 2, 3], from_str_with::<Vec<u8>>(input, "[1,2,3] ").unwrap());
+    assert_eq!(vec![1, 2, 3], from_reader_with::<Vec<u8>>(input, "[1,2,3] ").unwrap());
+    for text in &["[1,2,3]  ", "[1,2,3,4]", "\"abcdefgh\"", "[1,2,3] x"] {
+        let err = from_str_with::<IgnoredAny>(input, text).unwrap_err();
+        assert!(matches!(err.code(), ErrorCode::InputTooLong), "{}", text);
+        assert_eq!(8, err.offset());
+        let err = from_reader_with::<IgnoredAny>(input, text).unwrap_err();
+        assert!(matches!(err.code(), ErrorCode::InputTooLong), "{}", text);
+        assert_eq!(8, err.offset());
+    }
+    // 限制之内的截断仍然是 Eof
+    let err = from_str_with::<IgnoredAny>(input, "[1,2").unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::Eof));
+
+    // 读取器不会把超出限制的部分读入内存
+    let limits = Limits {
+        max_input_len: Some(1 << 20),
+        ..Limits::default()
+    };
+    let endless = io::repeat(b' ');
+    let mut de = Deserializer::from_reader(endless);
+    de.set_limits(limits);
+    let err = IgnoredAny::deserialize(&mut de).unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::InputTooLong));
+    assert_eq!(1 << 20, err.offset());
+
+    // 超出限制的输入本身是合法的 JSON，不属于语法错误
+    let limits = Limits {
+        max_string_len: Some(3),
+        max_number_len: Some(3),
+        max_elements: Some(2),
+        max_input_len: Some(16),
+    };
+    for input in &["\"abcd\"", "1234", "[1, 2, 3]", "[1]                 "] {
+        let err = from_str_with::<IgnoredAny>(limits, input).unwrap_err();
+        assert_eq!(Category::Data, err.classify(), "{}", input);
+    }
+    let mut de = Deserializer::from_str("[[1]]");
+    de.set_recursion_limit(1);
+    let err = IgnoredAny::deserialize(&mut de).unwrap_err();
+    assert_eq!(Category::Data, err.classify());
+}
+
+#[test]
+fn test_number_mode() {
+    use super::ch05_03_value::Value;
+
+    fn strict<'a, T: Deserialize<'a>>(input: &'a str) -> Result<T> {
+        let mut de = Deserializer::from_str(input);
+        de.set_number_mode(NumberMode::Strict);
+        let value = T::deserialize(&mut de)?;
+        de.end()?;
+        Ok(value)
+    }
+
+    // 严格模式：错误指向第一个不符合语法的字节
+    let cases = [
+        ("007", 1),
+        ("-01", 2),
+        ("00.5", 1),
+        ("0x1F", 1),
+        ("-0X1", 2),
+        ("+1", 0),
+        (".5", 0),
+        ("-.5", 1),
+        ("-", 1),
+        ("1.", 2),
+        ("1.e5", 2),
+        ("1e", 2),
+        ("1e+", 3),
+    ];
+    for &(input, offset) in &cases {
+        for err in &[strict::<Value>(input).unwrap_err(), strict::<f64>(input).unwrap_err()] {
+            assert!(matches!(err.code(), ErrorCode::InvalidNumber), "{}", input);
+            assert_eq!(offset, err.offset(), "{}", input);
+        }
+    }
+    let err = strict::<Vec<u8>>("[1, 02]").unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::InvalidNumber));
+    assert_eq!((1, 6), (err.line(), err.column()));
+    assert_eq!(0, strict::<u8>("0").unwrap());
+    assert_eq!(-0.5, strict::<f64>("-0.5").unwrap());
+    assert_eq!(0.0, strict::<f64>("0e2").unwrap());
+
+    // 宽松模式是默认设置，与早先的解析器一样只允许多余的前导零
+    assert_eq!(NumberMode::Lenient, NumberMode::default());
+    assert_eq!(7, from_str::<u32>("007").unwrap());
+    assert_eq!(-1, from_str::<i32>("-01").unwrap());
+    assert_eq!(0.5, from_str::<f64>("00.5").unwrap());
+    assert_eq!(crate::json!([7, -1, 0.5]), from_str::<Value>("[007, -01, 00.5]").unwrap());
+    let cases = [
+        ("+1", 0),
+        (".5", 0),
+        ("-.5", 1),
+        ("-", 1),
+        ("1.", 2),
+        ("1.e5", 2),
+        ("1e", 2),
+        ("1e+", 3),
+    ];
+    for &(input, offset) in &cases {
+        let err = from_str::<Value>(input).unwrap_err();
+        assert!(matches!(err.code(), ErrorCode::InvalidNumber), "{}", input);
+        assert_eq!(offset, err.offset(), "{}", input);
+    }
+    assert!(matches!(from_str::<u32>("0x1F").unwrap_err().code(), ErrorCode::TrailingCharacters));
+}
+
+#[test]
+fn test_char() {
+    assert_eq!('x', from_str::<char>("\"x\"").unwrap());
+    assert_eq!('\u{1F600}', from_str::<char>("\"\\ud83d\\ude00\"").unwrap());
+    assert_eq!('é', from_reader::<_, char>("\"é\"".as_bytes()).unwrap());
+    assert!(from_str::<char>("\"xy\"").is_err());
+    assert!(from_str::<char>("\"\"").is_err());
+    assert!(matches!(from_str::<char>("1").unwrap_err().code(), ErrorCode::ExpectedString));
+}
+
+// 离线回放 `fuzz/corpus/from_slice` 中的种子，以及它们的所有前缀和逐字节
+// 变异，检查 `fuzz/fuzz_targets/from_slice.rs` 中的调用都不会 panic
+#[test]
+fn test_fuzz_corpus() {
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::panic;
+
+    use serde::de::IgnoredAny;
+
+    use super::ch05_01_serializer as ser;
+    use super::ch05_03_value::Value;
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct Typed<'a> {
+        b: bool,
+        i: i8,
+        u: u16,
+        f: f32,
+        c: char,
+        s: &'a str,
+        o: Option<String>,
+        #[serde(with = "serde_bytes")]
+        bytes: Vec<u8>,
+        e: Vec<E>,
+        keys: BTreeMap<i32, ()>,
+        #[serde(flatten)]
+        rest: BTreeMap<String, IgnoredAny>,
+    }
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    enum E {
+        Unit,
+        Newtype(u64),
+        Tuple(i64, f64),
+        Struct { a: Value },
+    }
+
+    fn check(data: &[u8]) {
+        if let Ok(value) = from_slice::<Value>(data) {
+            let _ = ser::to_vec(&value);
+        }
+        let _ = from_slice::<Typed>(data);
+        let _ = from_slice::<IgnoredAny>(data);
+        let _ = from_reader::<_, Value>(data);
+        if let Ok(s) = str::from_utf8(data) {
+            let _ = from_str::<Value>(s);
+        }
+        for value in StreamDeserializer::<_, Value>::new(SliceRead::new(data)) {
+            let _ = value;
+        }
+
+        let mut de = Deserializer::from_slice(data);
+        de.set_bytes_mode(BytesMode::Base64);
+        de.set_limits(Limits {
+            max_string_len: Some(16),
+            max_number_len: Some(16),
+            max_elements: Some(4),
+            max_input_len: Some(64),
+        });
+        let _ = Typed::deserialize(&mut de);
+    }
+
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/fuzz/corpus/from_slice");
+    let mut seeds = 0;
+    for entry in fs::read_dir(dir).unwrap() {
+        let seed = fs::read(entry.unwrap().path()).unwrap();
+        seeds += 1;
+
+        let mut inputs = Vec::new();
+        for len in 0..=seed.len() {
+            inputs.push(seed[..len].to_vec());
+        }
+        for i in 0..seed.len() {
+            for &b in b"\"\\[]{},:0-eu \x00\x80\xff" {
+                let mut input = seed.clone();
+                input[i] = b;
+                inputs.push(input);
+            }
+            let mut input = seed.clone();
+            input.remove(i);
+            inputs.push(input);
+        }
+
+        for input in &inputs {
+            let result = panic::catch_unwind(|| check(input));
+            assert!(result.is_ok(), "panicked on {:?}", String::from_utf8_lossy(input));
+        }
+    }
+    assert!(seeds > 0);
+}
+
+#[test]
+fn test_find_special() {
+    // 逐字节查找的结果作为参照
+    fn naive(bytes: &[u8]) -> Option<usize> {
+        bytes.iter().position(|&b| is_special(b))
+    }
+
+    // 与特殊字节相邻的值，容易暴露按字判断时的借位问题
+    let fillers = [b' ', b'!', b'#', b'[', b']', b'a', 0x7f, 0x80, 0xa2, 0xdc, 0xff];
+    let specials = [b'"', b'\\', 0x00, 0x01, 0x1f];
+    for len in 0..24 {
+        for &filler in &fillers {
+            let mut bytes = vec![filler; len];
+            assert_eq!(None, find_special(&bytes));
+            for i in 0..len {
+                for &special in &specials {
+                    bytes[i] = special;
+                    assert_eq!(Some(i), find_special(&bytes));
+                    // 后面的特殊字节不影响结果
+                    for j in i + 1..len {
+                        bytes[j] = b'"';
+                        assert_eq!(naive(&bytes), find_special(&bytes));
+                        bytes[j] = filler;
+                    }
+                    bytes[i] = filler;
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_whitespace_len() {
+    // 逐字节查找的结果作为参照
+    fn naive(bytes: &[u8]) -> usize {
+        bytes.iter().position(|&b| !is_whitespace(b)).unwrap_or(bytes.len())
+    }
+
+    // 与空白字符只差一位或只差最高位的值，容易暴露按字判断时的误报
+    let whitespace = [b' ', b'\t', b'\n', b'\r'];
+    let others = [0x00, 0x08, 0x0b, 0x0c, 0x1f, 0x21, 0x2c, 0x89, 0x8a, 0x8d, 0xa0, 0xff];
+    for len in 0..24 {
+        for &ws in &whitespace {
+            let mut bytes = vec![ws; len];
+            assert_eq!(len, whitespace_len(&bytes));
+            for i in 0..len {
+                for &other in &others {
+                    bytes[i] = other;
+                    assert_eq!(i, whitespace_len(&bytes));
+                    // 混合不同的空白字符
+                    for j in 0..i {
+                        bytes[j] = whitespace[j % 4];
+                        assert_eq!(naive(&bytes), whitespace_len(&bytes));
+                    }
+                    bytes.iter_mut().for_each(|b| *b = ws);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_long_string() {
+    let text = "0123456789abcdef".repeat(100) + "é😀";
+    let json = format!("{:?}", text);
+    assert_eq!(text, from_str::<String>(&json).unwrap());
+    assert_eq!(text, from_slice::<&str>(json.as_bytes()).unwrap());
+    assert_eq!(text, from_reader::<_, String>(json.as_bytes()).unwrap());
+
+    // 转义和控制字符出现在各个位置上时都能找到
+    for i in 0..40 {
+        let escaped = format!("\"{}\\n{}\"", "x".repeat(i), "y".repeat(40 - i));
+        let expected = format!("{}\n{}", "x".repeat(i), "y".repeat(40 - i));
+        assert_eq!(expected, from_slice::<String>(escaped.as_bytes()).unwrap());
+        assert_eq!(expected, from_reader::<_, String>(escaped.as_bytes()).unwrap());
+
+        let control = format!("\"{}\t\"", "x".repeat(i));
+        let err = from_slice::<String>(control.as_bytes()).unwrap_err();
+        assert!(matches!(err.code(), ErrorCode::ControlCharacterInString));
+        assert_eq!(i + 1, err.offset());
+    }
+}
+
+#[test]
+fn test_ignored_any() {
+    use serde::de::IgnoredAny;
+
+    use super::ch05_03_value::Value;
+
+    // 跳过与解析为 `Value` 接受同样的输入，报告同样的错误
+    let inputs = [
+        r#"{"a":[1,-2.5e3,true,false,null],"b":{"c":"d\"é😀"}}"#,
+        r#" [ {} , [ ] , "" , 0 ] "#,
+        r#"[1,,2]"#,
+        r#"[1 2]"#,
+        r#"[1,]"#,
+        r#"{"a" 1}"#,
+        r#"{"a":1,}"#,
+        r#"{1:2}"#,
+        r#"{"a":1 "b":2}"#,
+        r#"[nul]"#,
+        r#"[tru]"#,
+        r#"[01]"#,
+        r#"[1.]"#,
+        r#"["a\x"]"#,
+        "[\"a\tb\"]",
+        r#"["\ud800"]"#,
+        r#"[1}"#,
+        r#"{"a":[}"#,
+        r#"[x]"#,
+        r#"["abc"#,
+        r#"[1,2"#,
+        r#"[1] 2"#,
+    ];
+    for input in &inputs {
+        let ignored = from_str::<IgnoredAny>(input).map(drop).map_err(|err| err.to_string());
+        let value = from_str::<Value>(input).map(drop).map_err(|err| err.to_string());
+        assert_eq!(value, ignored, "{}", input);
+    }
+
+    // 超出范围的数字语法正确，跳过时不报错
+    from_str::<IgnoredAny>("[1e400, 99999999999999999999999]").unwrap();
+    // 非法的 UTF-8 同样会被发现
+    assert!(from_slice::<IgnoredAny>(b"[\"\xff\"]").is_err());
+
+    // 结构体中未知的字段被跳过
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Pick<'a> {
+        id: u32,
+        name: &'a str,
+    }
+    let input = r#"{"skip":{"x":[1,{"y":"\n"},1e400]},"id":7,"more":[[[]]],"name":"n"}"#;
+    assert_eq!(Pick { id: 7, name: "n" }, from_str(input).unwrap());
+
+    // 错误发生在被跳过的字段中时，位置仍然准确
+    let err = from_str::<Pick>(r#"{"id":7,"skip":[1,,2],"name":"n"}"#).unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::Syntax));
+    assert_eq!(18, err.offset());
+
+    // 嵌套深度和元素个数的限制同样适用
+    let deep = format!(r#"{{"id":1,"name":"n","skip":{}{}}}"#, "[".repeat(200), "]".repeat(200));
+    let err = from_str::<Pick>(&deep).unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::RecursionLimitExceeded));
+    let mut de = Deserializer::from_str(r#"{"id":1,"skip":[1,2,3],"name":"n"}"#);
+    de.set_limits(Limits {
+        max_elements: Some(2),
+        ..Limits::default()
+    });
+    let err = Pick::deserialize(&mut de).unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::TooManyElements));
+}
+
+#[test]
+fn test_i128() {
+    use std::collections::BTreeMap;
+
+    use super::ch05_01_serializer::to_string;
+    use super::ch05_03_value::Value;
+
+    assert_eq!(u128::MAX, from_str::<u128>(&u128::MAX.to_string()).unwrap());
+    assert_eq!(i128::MIN, from_str::<i128>(&i128::MIN.to_string()).unwrap());
+    assert_eq!(i128::MAX, from_str::<i128>(&i128::MAX.to_string()).unwrap());
+    assert_eq!(0u128, from_str::<u128>("-0").unwrap());
+    assert_eq!(-1i128, from_str::<i128>(" -1 ").unwrap());
+
+    // 超出范围的整数
+    let err = from_str::<u128>("340282366920938463463374607431768211456").unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::NumberOutOfRange("u128")));
+    let err = from_str::<i128>("-170141183460469231731687303715884105729").unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::NumberOutOfRange("i128")));
+    let err = from_str::<u128>("-1").unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::NumberOutOfRange("u128")));
+    assert!(matches!(from_str::<i128>("1.5").unwrap_err().code(), ErrorCode::ExpectedInteger));
+
+    // 往返
+    for &n in &[0, 1, -1, i128::from(i64::MIN) - 1, i128::from(u64::MAX) + 1, i128::MIN, i128::MAX] {
+        assert_eq!(n, from_str::<i128>(&to_string(&n).unwrap()).unwrap());
+    }
+
+    // `deserialize_any` 把超出 64 位的整数交给 128 位的 visit 方法
+    #[derive(PartialEq, Debug)]
+    enum Id {
+        Small(u64),
+        Big(u128),
+        Negative(i128),
+    }
+
+    impl<'de> Deserialize<'de> for Id {
+        fn deserialize<D>(deserializer: D) -> std::result::Result<Id, D::Error>
+        where
+            D: de::Deserializer<'de>,
+        {
+            struct IdVisitor;
+
+            impl<'de> Visitor<'de> for IdVisitor {
+                type Value = Id;
+
+                fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    formatter.write_str("an integer")
+                }
+
+                fn visit_u64<E>(self, v: u64) -> std::result::Result<Id, E> {
+                    Ok(Id::Small(v))
+                }
+
+                fn visit_u128<E>(self, v: u128) -> std::result::Result<Id, E> {
+                    Ok(Id::Big(v))
+                }
+
+                fn visit_i128<E>(self, v: i128) -> std::result::Result<Id, E> {
+                    Ok(Id::Negative(v))
+                }
+            }
+
+            deserializer.deserialize_any(IdVisitor)
+        }
+    }
+    assert_eq!(Id::Small(7), from_str("7").unwrap());
+    assert_eq!(Id::Big(u128::from(u64::MAX) + 1), from_str("18446744073709551616").unwrap());
+    assert_eq!(Id::Negative(-(1 << 100)), from_str(&(-(1i128 << 100)).to_string()).unwrap());
+    let err = from_str::<Id>("340282366920938463463374607431768211456").unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::NumberOutOfRange("u128")));
+    // `Value` 只能保存 64 位的整数
+    assert!(from_str::<Value>("18446744073709551616").is_err());
+    assert_eq!(Value::from(u64::MAX), from_str::<Value>(&u64::MAX.to_string()).unwrap());
+
+    // 对象的键
+    let mut map = BTreeMap::new();
+    map.insert(u128::MAX, i128::MIN);
+    let json = to_string(&map).unwrap();
+    assert_eq!(format!(r#"{{"{}":{}}}"#, u128::MAX, i128::MIN), json);
+    assert_eq!(map, from_str::<BTreeMap<u128, i128>>(&json).unwrap());
+}
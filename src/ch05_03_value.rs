@@ -0,0 +1,682 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::Index;
+
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use super::ch05_01_serializer::to_fmt;
+
+// 任意的 JSON 值。形状事先未知的文档可以先反序列化为 Value 再检查
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Value {
+    #[default]
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(String),
+    Array(Vec<Value>),
+    Object(Map),
+}
+
+// JSON 对象。按键排序，序列化的输出是确定的
+pub type Map = BTreeMap<String, Value>;
+
+// JSON 数字。整数和浮点数分开保存，整数不会因为转换为 f64 而丢失精度
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Number {
+    n: N,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum N {
+    // 非负整数
+    PosInt(u64),
+    // 负整数
+    NegInt(i64),
+    // 总是有限的浮点数
+    Float(f64),
+}
+
+impl Number {
+    // 非有限的浮点数（NaN、无穷大）不是合法的 JSON 数字，返回 None
+    pub fn from_f64(f: f64) -> Option<Number> {
+        if f.is_finite() {
+            Some(Number { n: N::Float(f) })
+        } else {
+            None
+        }
+    }
+
+    pub fn is_u64(&self) -> bool {
+        matches!(self.n, N::PosInt(_))
+    }
+
+    pub fn is_i64(&self) -> bool {
+        match self.n {
+            N::PosInt(v) => v <= i64::MAX as u64,
+            N::NegInt(_) => true,
+            N::Float(_) => false,
+        }
+    }
+
+    pub fn is_f64(&self) -> bool {
+        matches!(self.n, N::Float(_))
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self.n {
+            N::PosInt(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self.n {
+            N::PosInt(v) => i64::try_from(v).ok(),
+            N::NegInt(v) => Some(v),
+            N::Float(_) => None,
+        }
+    }
+
+    // 整数总是可以转换为 f64，超过 2^53 的整数会损失精度
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.n {
+            N::PosInt(v) => Some(v as f64),
+            N::NegInt(v) => Some(v as f64),
+            N::Float(v) => Some(v),
+        }
+    }
+}
+
+impl From<u64> for Number {
+    fn from(v: u64) -> Self {
+        Number { n: N::PosInt(v) }
+    }
+}
+
+impl From<i64> for Number {
+    fn from(v: i64) -> Self {
+        // 非负整数统一保存为 PosInt，`Number::from(1i64) == Number::from(1u64)`
+        match u64::try_from(v) {
+            Ok(v) => Number { n: N::PosInt(v) },
+            Err(_) => Number { n: N::NegInt(v) },
+        }
+    }
+}
+
+// 与序列化器的输出相同，浮点数总是带有小数点或指数，例如 `1.0`
+impl fmt::Display for Number {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self.n {
+            N::PosInt(v) => fmt::Display::fmt(&v, formatter),
+            N::NegInt(v) => fmt::Display::fmt(&v, formatter),
+            N::Float(v) => formatter.write_str(ryu::Buffer::new().format_finite(v)),
+        }
+    }
+}
+
+impl Value {
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<&Number> {
+        match self {
+            Value::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_number().and_then(Number::as_u64)
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_number().and_then(Number::as_i64)
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_number().and_then(Number::as_f64)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match self {
+            Value::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&Map> {
+        match self {
+            Value::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    pub fn as_object_mut(&mut self) -> Option<&mut Map> {
+        match self {
+            Value::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    // 取对象中的成员，不是对象或者没有这个键时返回 None
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.as_object().and_then(|object| object.get(key))
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.as_object_mut().and_then(|object| object.get_mut(key))
+    }
+}
+
+// 以紧凑的 JSON 输出
+impl fmt::Display for Value {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        to_fmt(formatter, self).map_err(|_| fmt::Error)
+    }
+}
+
+impl Serialize for Number {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self.n {
+            N::PosInt(v) => serializer.serialize_u64(v),
+            N::NegInt(v) => serializer.serialize_i64(v),
+            N::Float(v) => serializer.serialize_f64(v),
+        }
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Value::Null => serializer.serialize_unit(),
+            Value::Bool(b) => serializer.serialize_bool(*b),
+            Value::Number(n) => n.serialize(serializer),
+            Value::String(s) => serializer.serialize_str(s),
+            Value::Array(array) => serializer.collect_seq(array),
+            Value::Object(object) => serializer.collect_map(object),
+        }
+    }
+}
+
+// Value 依赖 `deserialize_any`，只能从自描述的格式中反序列化
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D>(deserializer: D) -> Result<Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any valid JSON value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Number(v.into()))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Value, E> {
+        Ok(Value::Number(v.into()))
+    }
+
+    // 超出 64 位的整数无法放进 `Number`
+    fn visit_i128<E>(self, v: i128) -> Result<Value, E>
+    where
+        E: de::Error,
+    {
+        if let Ok(v) = i64::try_from(v) {
+            self.visit_i64(v)
+        } else if let Ok(v) = u64::try_from(v) {
+            self.visit_u64(v)
+        } else {
+            Err(E::custom("number out of range for Value"))
+        }
+    }
+
+    fn visit_u128<E>(self, v: u128) -> Result<Value, E>
+    where
+        E: de::Error,
+    {
+        match u64::try_from(v) {
+            Ok(v) => self.visit_u64(v),
+            Err(_) => Err(E::custom("number out of range for Value")),
+        }
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Value, E> {
+        Ok(Number::from_f64(v).map_or(Value::Null, Value::Number))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::String(v.to_owned()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Value, E> {
+        Ok(Value::String(v))
+    }
+
+    fn visit_none<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Deserialize::deserialize(deserializer)
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut array = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(element) = seq.next_element()? {
+            array.push(element);
+        }
+        Ok(Value::Array(array))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut object = Map::new();
+        while let Some((key, value)) = map.next_entry()? {
+            object.insert(key, value);
+        }
+        Ok(Value::Object(object))
+    }
+}
+
+impl<'de> Deserialize<'de> for Number {
+    fn deserialize<D>(deserializer: D) -> Result<Number, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Value::deserialize(deserializer)? {
+            Value::Number(n) => Ok(n),
+            _ => Err(de::Error::custom("expected a JSON number")),
+        }
+    }
+}
+
+// 与 serde_json 一样，不存在的键、越界的下标以及类型不匹配都得到
+// `Value::Null`，而不是 panic，便于连续索引 `value["a"][0]["b"]`
+static NULL: Value = Value::Null;
+
+impl<'a> Index<&'a str> for Value {
+    type Output = Value;
+
+    fn index(&self, key: &'a str) -> &Value {
+        self.get(key).unwrap_or(&NULL)
+    }
+}
+
+impl Index<usize> for Value {
+    type Output = Value;
+
+    fn index(&self, index: usize) -> &Value {
+        self.as_array()
+            .and_then(|array| array.get(index))
+            .unwrap_or(&NULL)
+    }
+}
+
+// 与基本类型比较，例如 `value["name"] == "Alice"`、`value["age"] == 30`
+impl PartialEq<str> for Value {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == Some(other)
+    }
+}
+
+impl<'a> PartialEq<&'a str> for Value {
+    fn eq(&self, other: &&'a str) -> bool {
+        self.as_str() == Some(*other)
+    }
+}
+
+impl PartialEq<String> for Value {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == Some(other.as_str())
+    }
+}
+
+impl PartialEq<Value> for str {
+    fn eq(&self, other: &Value) -> bool {
+        other == self
+    }
+}
+
+impl PartialEq<Value> for &str {
+    fn eq(&self, other: &Value) -> bool {
+        other == self
+    }
+}
+
+impl PartialEq<Value> for String {
+    fn eq(&self, other: &Value) -> bool {
+        other == self
+    }
+}
+
+impl PartialEq<bool> for Value {
+    fn eq(&self, other: &bool) -> bool {
+        self.as_bool() == Some(*other)
+    }
+}
+
+impl PartialEq<Value> for bool {
+    fn eq(&self, other: &Value) -> bool {
+        other == self
+    }
+}
+
+macro_rules! partial_eq_num {
+    ($($ty:ty => $as:ident as $conv:ty),*) => {
+        $(
+            impl PartialEq<$ty> for Value {
+                fn eq(&self, other: &$ty) -> bool {
+                    self.$as() == Some(*other as $conv)
+                }
+            }
+
+            impl PartialEq<Value> for $ty {
+                fn eq(&self, other: &Value) -> bool {
+                    other == self
+                }
+            }
+        )*
+    };
+}
+
+partial_eq_num! {
+    i8 => as_i64 as i64,
+    i16 => as_i64 as i64,
+    i32 => as_i64 as i64,
+    i64 => as_i64 as i64,
+    isize => as_i64 as i64,
+    u8 => as_u64 as u64,
+    u16 => as_u64 as u64,
+    u32 => as_u64 as u64,
+    u64 => as_u64 as u64,
+    usize => as_u64 as u64,
+    f32 => as_f64 as f64,
+    f64 => as_f64 as f64
+}
+
+// 从基本类型构造 Value，`json!` 宏中的表达式通过这些实现转换
+macro_rules! from_integer {
+    ($($ty:ty => $wide:ty),*) => {
+        $(
+            impl From<$ty> for Value {
+                fn from(v: $ty) -> Self {
+                    Value::Number(Number::from(v as $wide))
+                }
+            }
+        )*
+    };
+}
+
+from_integer! {
+    i8 => i64,
+    i16 => i64,
+    i32 => i64,
+    i64 => i64,
+    isize => i64,
+    u8 => u64,
+    u16 => u64,
+    u32 => u64,
+    u64 => u64,
+    usize => u64
+}
+
+// 非有限的浮点数无法表示为 JSON 数字，转换为 `Value::Null`
+impl From<f32> for Value {
+    fn from(v: f32) -> Self {
+        Value::from(f64::from(v))
+    }
+}
+
+impl From<f64> for Value {
+    fn from(v: f64) -> Self {
+        Number::from_f64(v).map_or(Value::Null, Value::Number)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(v: bool) -> Self {
+        Value::Bool(v)
+    }
+}
+
+impl From<Number> for Value {
+    fn from(v: Number) -> Self {
+        Value::Number(v)
+    }
+}
+
+impl<'a> From<&'a str> for Value {
+    fn from(v: &'a str) -> Self {
+        Value::String(v.to_owned())
+    }
+}
+
+impl From<String> for Value {
+    fn from(v: String) -> Self {
+        Value::String(v)
+    }
+}
+
+impl From<()> for Value {
+    fn from((): ()) -> Self {
+        Value::Null
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(v: Option<T>) -> Self {
+        v.map_or(Value::Null, Into::into)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(v: Vec<T>) -> Self {
+        Value::Array(v.into_iter().map(Into::into).collect())
+    }
+}
+
+impl From<Map> for Value {
+    fn from(v: Map) -> Self {
+        Value::Object(v)
+    }
+}
+
+// 用接近 JSON 的语法构造 Value：
+//
+//     let value = json!({
+//         "name": "Alice",
+//         "age": 30,
+//         "tags": ["a", "b"],
+//         "extra": null,
+//     });
+//
+// 对象的键是字符串字面量或者括号括起来的表达式，值可以是任意能转换为
+// Value 的表达式
+#[macro_export]
+macro_rules! json {
+    // 逐个解析数组元素，已解析的元素放在方括号中
+    (@array [$($elems:expr,)*]) => {
+        vec![$($elems,)*]
+    };
+    (@array [$($elems:expr,)*] null $(, $($rest:tt)*)?) => {
+        $crate::json!(@array [$($elems,)* $crate::json!(null),] $($($rest)*)?)
+    };
+    (@array [$($elems:expr,)*] [$($array:tt)*] $(, $($rest:tt)*)?) => {
+        $crate::json!(@array [$($elems,)* $crate::json!([$($array)*]),] $($($rest)*)?)
+    };
+    (@array [$($elems:expr,)*] {$($object:tt)*} $(, $($rest:tt)*)?) => {
+        $crate::json!(@array [$($elems,)* $crate::json!({$($object)*}),] $($($rest)*)?)
+    };
+    (@array [$($elems:expr,)*] $next:expr $(, $($rest:tt)*)?) => {
+        $crate::json!(@array [$($elems,)* $crate::json!($next),] $($($rest)*)?)
+    };
+
+    // 逐个解析对象成员，插入到 `$object` 中
+    (@object $object:ident) => {};
+    (@object $object:ident $key:tt : null $(, $($rest:tt)*)?) => {
+        $object.insert(::std::string::String::from($key), $crate::json!(null));
+        $crate::json!(@object $object $($($rest)*)?);
+    };
+    (@object $object:ident $key:tt : [$($array:tt)*] $(, $($rest:tt)*)?) => {
+        $object.insert(::std::string::String::from($key), $crate::json!([$($array)*]));
+        $crate::json!(@object $object $($($rest)*)?);
+    };
+    (@object $object:ident $key:tt : {$($inner:tt)*} $(, $($rest:tt)*)?) => {
+        $object.insert(::std::string::String::from($key), $crate::json!({$($inner)*}));
+        $crate::json!(@object $object $($($rest)*)?);
+    };
+    (@object $object:ident $key:tt : $value:expr $(, $($rest:tt)*)?) => {
+        $object.insert(::std::string::String::from($key), $crate::json!($value));
+        $crate::json!(@object $object $($($rest)*)?);
+    };
+
+    (null) => {
+        $crate::ch05_03_value::Value::Null
+    };
+    ([$($tt:tt)*]) => {
+        $crate::ch05_03_value::Value::Array($crate::json!(@array [] $($tt)*))
+    };
+    ({$($tt:tt)*}) => {{
+        #[allow(unused_mut)]
+        let mut object = $crate::ch05_03_value::Map::new();
+        $crate::json!(@object object $($tt)*);
+        $crate::ch05_03_value::Value::Object(object)
+    }};
+    ($other:expr) => {
+        $crate::ch05_03_value::Value::from($other)
+    };
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn test_round_trip() {
+    use super::ch05_01_serializer::to_string;
+    use super::ch05_02_deserializer::from_str;
+
+    let j = r#"{"a":[1,-2,3.5,"x\ny",true,null],"b":{},"c":[]}"#;
+    let value: Value = from_str(j).unwrap();
+    assert_eq!(j, to_string(&value).unwrap());
+    assert_eq!(j, value.to_string());
+
+    let expected = crate::json!({
+        "a": [1, -2, 3.5, "x\ny", true, null],
+        "b": {},
+        "c": [],
+    });
+    assert_eq!(expected, value);
+
+    // 空白和转义不影响结果
+    let value: Value = from_str(" [ \"\\u00e9\" , { \"k\" : 1 } ] ").unwrap();
+    assert_eq!(crate::json!(["é", {"k": 1}]), value);
+}
+
+#[test]
+fn test_index() {
+    let value = crate::json!({
+        "name": "Alice",
+        "age": 30,
+        "score": -1.5,
+        "tags": ["a", "b"],
+        "admin": false,
+        "extra": null,
+    });
+
+    assert_eq!(value["name"], "Alice");
+    assert_eq!("Alice", value["name"]);
+    assert_eq!(value["age"], 30);
+    assert_eq!(value["age"], 30u8);
+    assert_eq!(value["score"], -1.5);
+    assert_eq!(value["tags"][1], "b");
+    assert_eq!(value["admin"], false);
+    assert!(value["extra"].is_null());
+    // 不存在的键、越界的下标和类型不匹配都得到 Null
+    assert!(value["missing"]["deeper"].is_null());
+    assert!(value["tags"][5].is_null());
+    assert!(value["name"][0].is_null());
+
+    assert_eq!(Some(30), value["age"].as_u64());
+    assert_eq!(Some(30), value["age"].as_i64());
+    assert_eq!(Some(30.0), value["age"].as_f64());
+    assert_eq!(None, value["score"].as_i64());
+    assert_eq!(Some("Alice"), value["name"].as_str());
+    assert_eq!(Some(2), value["tags"].as_array().map(Vec::len));
+    assert_eq!(Some(6), value.as_object().map(Map::len));
+    assert_ne!(value["age"], "30");
+}
+
+#[test]
+fn test_json_macro() {
+    let key = "dynamic";
+    let n = 7;
+    let value = crate::json!({
+        (key): n + 1,
+        "nested": {"list": [n, -n, [null]], "s": format!("{}!", n)},
+        "opt": Option::<u32>::None,
+    });
+    assert_eq!(value["dynamic"], 8);
+    assert_eq!(value["nested"]["list"][1], -7);
+    assert!(value["nested"]["list"][2][0].is_null());
+    assert_eq!(value["nested"]["s"], "7!");
+    assert!(value["opt"].is_null());
+
+    assert_eq!(Value::Null, crate::json!(null));
+    assert_eq!(Value::Array(vec![]), crate::json!([]));
+    assert_eq!(Value::Null, crate::json!(f64::NAN));
+}
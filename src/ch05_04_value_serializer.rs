@@ -0,0 +1,472 @@
+use std::convert::TryFrom;
+use std::fmt;
+
+use serde::{ser, Serialize};
+
+use super::ch05_00_error::{Error, ErrorCode, Result};
+use super::ch05_01_serializer::{MapKeySerializer, MapKeyWriter};
+use super::ch05_03_value::{Map, Number, Value};
+
+// 把任意 `T: Serialize` 直接转换为 `Value`，不经过 JSON 文本
+pub fn to_value<T>(value: &T) -> Result<Value>
+where
+    T: ?Sized + Serialize,
+{
+    value.serialize(Serializer)
+}
+
+// 输出是 `Value` 的序列化器。与 `ch05_01_serializer::Serializer` 使用同样的
+// JSON 表示：unit 和 None 为 null，枚举为外部标记形式，字节为数字数组
+pub struct Serializer;
+
+impl ser::Serializer for Serializer {
+    type Ok = Value;
+    type Error = Error;
+
+    type SerializeSeq = SerializeVec;
+    type SerializeTuple = SerializeVec;
+    type SerializeTupleStruct = SerializeVec;
+    type SerializeTupleVariant = SerializeTupleVariant;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeMap;
+    type SerializeStructVariant = SerializeStructVariant;
+
+    fn serialize_bool(self, v: bool) -> Result<Value> {
+        Ok(Value::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value> {
+        Ok(Value::Number(v.into()))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value> {
+        self.serialize_u64(u64::from(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value> {
+        self.serialize_u64(u64::from(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value> {
+        self.serialize_u64(u64::from(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value> {
+        Ok(Value::Number(v.into()))
+    }
+
+    // `Number` 只能保存 64 位的整数，更大的 128 位整数无法转换
+    fn serialize_i128(self, v: i128) -> Result<Value> {
+        if let Ok(v) = i64::try_from(v) {
+            self.serialize_i64(v)
+        } else if let Ok(v) = u64::try_from(v) {
+            self.serialize_u64(v)
+        } else {
+            Err(ErrorCode::NumberOutOfRange("Value").into())
+        }
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Value> {
+        match u64::try_from(v) {
+            Ok(v) => self.serialize_u64(v),
+            Err(_) => Err(ErrorCode::NumberOutOfRange("Value").into()),
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value> {
+        self.serialize_f64(f64::from(v))
+    }
+
+    // NaN 和无穷大无法表示为 JSON 数字，与 `Value::from(f64)` 一样转换为 null
+    fn serialize_f64(self, v: f64) -> Result<Value> {
+        Ok(Number::from_f64(v).map_or(Value::Null, Value::Number))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value> {
+        Ok(Value::String(v.to_owned()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value> {
+        Ok(Value::Array(v.iter().map(|&b| Value::from(b)).collect()))
+    }
+
+    fn serialize_none(self) -> Result<Value> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Value>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Value> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Value>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    // `{ NAME: VALUE }`
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value>
+    where
+        T: ?Sized + Serialize,
+    {
+        Ok(wrap_variant(variant, to_value(value)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Ok(SerializeVec {
+            vec: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.serialize_seq(Some(len))
+    }
+
+    // `{ NAME: [DATA...] }`
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Ok(SerializeTupleVariant {
+            name: variant,
+            vec: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Ok(SerializeMap {
+            map: Map::new(),
+            next_key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
+        self.serialize_map(Some(len))
+    }
+
+    // `{ NAME: { K: V, ... } }`
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Ok(SerializeStructVariant {
+            name: variant,
+            map: Map::new(),
+        })
+    }
+}
+
+pub struct SerializeVec {
+    vec: Vec<Value>,
+}
+
+pub struct SerializeTupleVariant {
+    name: &'static str,
+    vec: Vec<Value>,
+}
+
+pub struct SerializeMap {
+    map: Map,
+    // `serialize_key` 与 `serialize_value` 分开调用，键暂存在这里
+    next_key: Option<String>,
+}
+
+pub struct SerializeStructVariant {
+    name: &'static str,
+    map: Map,
+}
+
+// 把枚举的内容包装为 `{ NAME: VALUE }`
+fn wrap_variant(name: &'static str, value: Value) -> Value {
+    let mut object = Map::new();
+    object.insert(name.to_owned(), value);
+    Value::Object(object)
+}
+
+impl ser::SerializeSeq for SerializeVec {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.vec.push(to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value> {
+        Ok(Value::Array(self.vec))
+    }
+}
+
+impl ser::SerializeTuple for SerializeVec {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeVec {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeTupleVariant {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.vec.push(to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value> {
+        Ok(wrap_variant(self.name, Value::Array(self.vec)))
+    }
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = Value;
+    type Error = Error;
+
+    // 与文本序列化器的规则相同，见 `ch05_01_serializer::MapKeySerializer`
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.next_key = Some(key.serialize(MapKeySerializer { writer: KeyString })?);
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        match self.next_key.take() {
+            Some(key) => {
+                self.map.insert(key, to_value(value)?);
+                Ok(())
+            }
+            None => Err(ser::Error::custom("serialize_value called before serialize_key")),
+        }
+    }
+
+    fn end(self) -> Result<Value> {
+        Ok(Value::Object(self.map))
+    }
+}
+
+impl ser::SerializeStruct for SerializeMap {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.map.insert(key.to_owned(), to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeStructVariant {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.map.insert(key.to_owned(), to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value> {
+        Ok(wrap_variant(self.name, Value::Object(self.map)))
+    }
+}
+
+// 把对象的键转换为 `String`，整数和布尔值转换为它们的文本形式
+struct KeyString;
+
+impl MapKeyWriter for KeyString {
+    type Ok = String;
+
+    fn write_str(self, v: &str) -> Result<String> {
+        Ok(v.to_owned())
+    }
+
+    fn write_quoted<T>(self, v: T) -> Result<String>
+    where
+        T: Serialize + fmt::Display,
+    {
+        Ok(v.to_string())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn test_to_value() {
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    enum E {
+        Unit,
+        Newtype(i32),
+        Tuple(u32, u32),
+        Struct { a: Option<u32> },
+    }
+
+    #[derive(Serialize)]
+    struct Test {
+        int: u64,
+        float: f64,
+        name: String,
+        seq: Vec<E>,
+        map: BTreeMap<String, bool>,
+        unit: (),
+    }
+
+    let mut map = BTreeMap::new();
+    map.insert("k".to_owned(), true);
+    let test = Test {
+        int: u64::MAX,
+        float: -0.5,
+        name: "a\"b".to_owned(),
+        seq: vec![E::Unit, E::Newtype(-1), E::Tuple(1, 2), E::Struct { a: None }],
+        map,
+        unit: (),
+    };
+    let expected = crate::json!({
+        "int": u64::MAX,
+        "float": -0.5,
+        "name": "a\"b",
+        "seq": ["Unit", {"Newtype": -1}, {"Tuple": [1, 2]}, {"Struct": {"a": null}}],
+        "map": {"k": true},
+        "unit": null,
+    });
+    assert_eq!(expected, to_value(&test).unwrap());
+
+    // 与文本序列化器的输出表示同一个值（Map 按键排序，成员顺序可能不同）
+    let text = super::ch05_01_serializer::to_string(&test).unwrap();
+    let parsed: Value = super::ch05_02_deserializer::from_str(&text).unwrap();
+    assert_eq!(parsed, to_value(&test).unwrap());
+
+    assert_eq!(Value::Null, to_value(&f64::NAN).unwrap());
+    assert_eq!(crate::json!([1, 2]), to_value(&b"\x01\x02"[..]).unwrap());
+
+    let mut map = BTreeMap::new();
+    map.insert(1u32, 2);
+    assert_eq!(crate::json!({"1": 2}), to_value(&map).unwrap());
+    let mut map = BTreeMap::new();
+    map.insert(vec![1], 2);
+    let err = to_value(&map).unwrap_err();
+    assert!(matches!(err.code(), super::ch05_00_error::ErrorCode::KeyMustBeAString));
+    // 键的规则与文本序列化器共用
+    let mut map = BTreeMap::new();
+    map.insert('é', 1);
+    assert_eq!(crate::json!({"é": 1}), to_value(&map).unwrap());
+    let mut map = BTreeMap::new();
+    map.insert(false, 1);
+    let text = super::ch05_01_serializer::to_string(&map).unwrap();
+    assert_eq!(r#"{"false":1}"#, text);
+    assert_eq!(crate::json!({"false": 1}), to_value(&map).unwrap());
+
+    // 64 位以内的 128 位整数可以转换
+    assert_eq!(crate::json!(u64::MAX), to_value(&u128::from(u64::MAX)).unwrap());
+    assert_eq!(crate::json!(-1), to_value(&-1i128).unwrap());
+    assert_eq!(crate::json!(u64::MAX), to_value(&i128::from(u64::MAX)).unwrap());
+    let err = to_value(&(u128::from(u64::MAX) + 1)).unwrap_err();
+    assert!(matches!(err.code(), ErrorCode::NumberOutOfRange("Value")));
+    assert!(to_value(&(i128::from(i64::MIN) - 1)).is_err());
+    let mut map = BTreeMap::new();
+    map.insert(u128::MAX, 0);
+    let key = u128::MAX.to_string();
+    assert_eq!(crate::json!({ key: 0 }), to_value(&map).unwrap());
+}
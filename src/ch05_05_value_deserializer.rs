@@ -0,0 +1,469 @@
+use std::collections::btree_map;
+use std::vec;
+
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess,
+    Unexpected, VariantAccess, Visitor,
+};
+use serde::forward_to_deserialize_any;
+
+use super::ch05_00_error::{Error, Result};
+use super::ch05_03_value::{Map, Number, Value};
+
+// 把 `Value` 转换为任意 `T: DeserializeOwned`，不经过 JSON 文本
+pub fn from_value<T>(value: Value) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    T::deserialize(value)
+}
+
+impl Value {
+    // 错误信息中描述这个值
+    fn unexpected(&self) -> Unexpected<'_> {
+        match self {
+            Value::Null => Unexpected::Unit,
+            Value::Bool(b) => Unexpected::Bool(*b),
+            Value::Number(n) => n.unexpected(),
+            Value::String(s) => Unexpected::Str(s),
+            Value::Array(_) => Unexpected::Seq,
+            Value::Object(_) => Unexpected::Map,
+        }
+    }
+
+    fn invalid_type<E: de::Error>(&self, exp: &dyn de::Expected) -> E {
+        de::Error::invalid_type(self.unexpected(), exp)
+    }
+}
+
+impl Number {
+    fn unexpected(&self) -> Unexpected<'static> {
+        if let Some(v) = self.as_u64() {
+            Unexpected::Unsigned(v)
+        } else if let Some(v) = self.as_i64() {
+            Unexpected::Signed(v)
+        } else {
+            Unexpected::Float(self.as_f64().unwrap_or(f64::NAN))
+        }
+    }
+
+    // 与文本反序列化器的 `deserialize_number` 一样：非负整数按 u64，负整数
+    // 按 i64，其余按 f64 交给 visitor
+    fn deserialize_any<'de, V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if let Some(v) = self.as_u64() {
+            visitor.visit_u64(v)
+        } else if let Some(v) = self.as_i64() {
+            visitor.visit_i64(v)
+        } else {
+            visitor.visit_f64(self.as_f64().unwrap_or(f64::NAN))
+        }
+    }
+}
+
+// Value 本身就是一个自描述的反序列化器：每个变体都知道自己的类型
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(b) => visitor.visit_bool(b),
+            Value::Number(n) => n.deserialize_any(visitor),
+            Value::String(s) => visitor.visit_string(s),
+            Value::Array(array) => visit_array(array, visitor),
+            Value::Object(object) => visit_object(object, visitor),
+        }
+    }
+
+    // null 表示 None，其余的值都是 Some
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // 外部标记的枚举：unit variant 为字符串，其余为只有一个成员的对象
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let (variant, value) = match self {
+            Value::String(variant) => (variant, None),
+            Value::Object(object) => {
+                let mut iter = object.into_iter();
+                match (iter.next(), iter.next()) {
+                    (Some((variant, value)), None) => (variant, Some(value)),
+                    _ => {
+                        return Err(de::Error::invalid_value(
+                            Unexpected::Map,
+                            &"map with a single key",
+                        ))
+                    }
+                }
+            }
+            other => return Err(other.invalid_type(&"string or map")),
+        };
+        visitor.visit_enum(EnumDeserializer { variant, value })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Value {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+fn visit_array<'de, V>(array: Vec<Value>, visitor: V) -> Result<V::Value>
+where
+    V: Visitor<'de>,
+{
+    let len = array.len();
+    let mut deserializer = SeqDeserializer {
+        iter: array.into_iter(),
+    };
+    let value = visitor.visit_seq(&mut deserializer)?;
+    // visitor 必须消费全部元素，例如 `[1, 2, 3]` 不能反序列化为 `(u32, u32)`
+    if deserializer.iter.len() == 0 {
+        Ok(value)
+    } else {
+        Err(de::Error::invalid_length(len, &"fewer elements in array"))
+    }
+}
+
+fn visit_object<'de, V>(object: Map, visitor: V) -> Result<V::Value>
+where
+    V: Visitor<'de>,
+{
+    let len = object.len();
+    let mut deserializer = MapDeserializer {
+        iter: object.into_iter(),
+        value: None,
+    };
+    let value = visitor.visit_map(&mut deserializer)?;
+    if deserializer.iter.len() == 0 {
+        Ok(value)
+    } else {
+        Err(de::Error::invalid_length(len, &"fewer elements in map"))
+    }
+}
+
+struct SeqDeserializer {
+    iter: vec::IntoIter<Value>,
+}
+
+impl<'de> SeqAccess<'de> for SeqDeserializer {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            Some(value) => seed.deserialize(value).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct MapDeserializer {
+    iter: btree_map::IntoIter<String, Value>,
+    // `next_key_seed` 取出的键值对中的值，留给 `next_value_seed`
+    value: Option<Value>,
+}
+
+impl<'de> MapAccess<'de> for MapDeserializer {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(MapKeyDeserializer { key }).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        match self.value.take() {
+            Some(value) => seed.deserialize(value),
+            None => Err(de::Error::custom("value is missing")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+// 对象的键。与文本反序列化器的 `MapKey` 一样，整数和布尔值类型的键从
+// 字符串中解析，其余类型按字符串处理
+struct MapKeyDeserializer {
+    key: String,
+}
+
+macro_rules! deserialize_parsed_key {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                match self.key.parse() {
+                    Ok(v) => visitor.$visit(v),
+                    Err(_) => Err(de::Error::invalid_value(Unexpected::Str(&self.key), &visitor)),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for MapKeyDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_string(self.key)
+    }
+
+    deserialize_parsed_key! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_i128 => visit_i128,
+        deserialize_u128 => visit_u128,
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Value::String(self.key).deserialize_enum(name, variants, visitor)
+    }
+
+    forward_to_deserialize_any! {
+        f32 f64 char str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct EnumDeserializer {
+    variant: String,
+    // unit variant 没有内容
+    value: Option<Value>,
+}
+
+impl<'de> EnumAccess<'de> for EnumDeserializer {
+    type Error = Error;
+    type Variant = VariantDeserializer;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, VariantDeserializer)>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(Value::String(self.variant))?;
+        Ok((variant, VariantDeserializer { value: self.value }))
+    }
+}
+
+struct VariantDeserializer {
+    value: Option<Value>,
+}
+
+impl<'de> VariantAccess<'de> for VariantDeserializer {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        match self.value {
+            None => Ok(()),
+            Some(value) => Err(value.invalid_type(&"unit variant")),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.value {
+            Some(value) => seed.deserialize(value),
+            None => Err(de::Error::invalid_type(Unexpected::UnitVariant, &"newtype variant")),
+        }
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Some(Value::Array(array)) => visit_array(array, visitor),
+            Some(other) => Err(other.invalid_type(&"tuple variant")),
+            None => Err(de::Error::invalid_type(Unexpected::UnitVariant, &"tuple variant")),
+        }
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.value {
+            Some(Value::Object(object)) => visit_object(object, visitor),
+            Some(other) => Err(other.invalid_type(&"struct variant")),
+            None => Err(de::Error::invalid_type(Unexpected::UnitVariant, &"struct variant")),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn test_from_value() {
+    use serde::Deserialize;
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    enum E {
+        Unit,
+        Newtype(i32),
+        Tuple(u32, u32),
+        Struct { a: Option<u32> },
+    }
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Test {
+        int: u64,
+        float: f64,
+        name: String,
+        seq: Vec<E>,
+        unit: (),
+    }
+
+    let value = crate::json!({
+        "int": 1,
+        "float": 2,
+        "name": "a",
+        "seq": ["Unit", {"Newtype": -1}, {"Tuple": [1, 2]}, {"Struct": {"a": null}}],
+        "unit": null,
+    });
+    let expected = Test {
+        int: 1,
+        float: 2.0,
+        name: "a".to_owned(),
+        seq: vec![E::Unit, E::Newtype(-1), E::Tuple(1, 2), E::Struct { a: None }],
+        unit: (),
+    };
+    assert_eq!(expected, from_value(value).unwrap());
+
+    let err = from_value::<u8>(crate::json!(256)).unwrap_err();
+    assert_eq!("invalid value: integer `256`, expected u8", err.to_string());
+    let err = from_value::<(u32, u32)>(crate::json!([1, 2, 3])).unwrap_err();
+    assert_eq!("invalid length 3, expected fewer elements in array", err.to_string());
+    assert!(from_value::<E>(crate::json!({"Unit": null, "Tuple": []})).is_err());
+    assert!(from_value::<E>(crate::json!({"Newtype": "x"})).is_err());
+
+    let map: std::collections::BTreeMap<u8, bool> =
+        from_value(crate::json!({"1": true, "2": false})).unwrap();
+    assert_eq!(vec![(1, true), (2, false)], map.into_iter().collect::<Vec<_>>());
+    let err = from_value::<std::collections::BTreeMap<u8, bool>>(crate::json!({"x": true}));
+    assert!(err.is_err());
+}
+
+#[test]
+fn test_patch_round_trip() {
+    use serde::{Deserialize, Serialize};
+
+    use super::ch05_04_value_serializer::to_value;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Request {
+        id: u64,
+        user: String,
+        tags: Vec<String>,
+        retries: Option<u8>,
+    }
+
+    let request = Request {
+        id: u64::MAX,
+        user: "alice".to_owned(),
+        tags: vec!["a".to_owned()],
+        retries: None,
+    };
+    // 修改树上的几个字段后再转换为具体的结构体，u64::MAX 不会丢失精度
+    let mut value = to_value(&request).unwrap();
+    *value.get_mut("user").unwrap() = Value::from("bob");
+    value.get_mut("tags").and_then(Value::as_array_mut).unwrap().push("b".into());
+    value.as_object_mut().unwrap().insert("retries".to_owned(), 3.into());
+
+    let expected = Request {
+        id: u64::MAX,
+        user: "bob".to_owned(),
+        tags: vec!["a".to_owned(), "b".to_owned()],
+        retries: Some(3),
+    };
+    assert_eq!(expected, from_value(value).unwrap());
+}
@@ -3,6 +3,9 @@ pub mod ch04_custom_serde;
 pub mod ch05_00_error;
 pub mod ch05_01_serializer;
 pub mod ch05_02_deserializer;
+pub mod ch05_03_value;
+pub mod ch05_04_value_serializer;
+pub mod ch05_05_value_deserializer;
 
 #[cfg(test)]
 mod tests {